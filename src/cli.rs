@@ -7,6 +7,45 @@ pub fn build() -> App<'static, 'static> {
         .arg(
             Arg::with_name("file")
                 .help("The file to open")
-                .required(true),
+                .required_unless_one(&["server", "replay"]),
+        )
+        .arg(
+            Arg::with_name("core")
+                .long("core")
+                .value_name("PATH")
+                .help("Spawn the given xi-core binary instead of the embedded core")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("server")
+                .long("server")
+                .value_name("SOCKET")
+                .help("Share a xi-core with the clients connecting to the given Unix socket")
+                .conflicts_with_all(&["file", "core", "connect"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("connect")
+                .long("connect")
+                .value_name("SOCKET")
+                .help("Use the xi-core shared by a server on the given Unix socket")
+                .conflicts_with("core")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("record")
+                .long("record")
+                .value_name("FILE")
+                .help("Record the messages exchanged with xi-core into the given file")
+                .conflicts_with("server")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("replay")
+                .long("replay")
+                .value_name("FILE")
+                .help("Replay a recording and print the resulting screen")
+                .conflicts_with_all(&["file", "server", "record"])
+                .takes_value(true),
         )
 }
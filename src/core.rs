@@ -1,22 +1,142 @@
-use std::io::{self, BufRead, Read, Write};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use std::sync::mpsc::{channel, Receiver, Sender};
-use std::thread;
+use std::thread::{self, JoinHandle};
 
+use crate::record::{Direction, Recorder};
+use crate::split::SplitTree;
+
+use failure::Error;
 use serde_json::Value;
 use xi_core_lib::XiCore;
-use xi_rpc::RpcLoop;
+use xi_rpc::{ReadError, RpcLoop};
+
+/// The notification given to the RPC loop of the EventController in place of
+/// each client message. The message itself is received from the channel
+/// given by `Reader::client_messages`.
+pub const CLIENT_MESSAGE_METHOD: &str = "client_message";
+
+/// A message between the InputController and the EventController.
+///
+/// These messages never reach the core, they share the channel read by the
+/// EventController with the core messages but keep their own type. Their
+/// JSON form is only used by the recordings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum ClientMessage {
+    SetPathForView {
+        view_id: String,
+        path: String,
+    },
+    /// The tab pages, the panes of the current one and the focused view.
+    SetLayout {
+        tabs: Vec<String>,
+        current_tab: usize,
+        panes: SplitTree,
+        focused_view: String,
+    },
+    RemoveView {
+        view_id: String,
+    },
+    /// The terminal has been resized to the given size, in cells.
+    Resize {
+        width: u32,
+        height: u32,
+    },
+    /// Change an option handled by the client instead of the core.
+    SetOption {
+        key: String,
+        value: Value,
+    },
+    WriteToFile {
+        view_id: String,
+        file_path: Option<String>,
+    },
+    Substitute {
+        view_id: String,
+        substitution: Substitution,
+    },
+    ShowCommandLine {
+        prompt: String,
+        content: String,
+        cursor: usize,
+    },
+    HideCommandLine {},
+    Alert {
+        msg: String,
+    },
+    AddStatusItem {
+        key: String,
+        value: String,
+        alignment: String,
+    },
+    UpdateStatusItem {
+        key: String,
+        value: String,
+    },
+    /// Stop the EventController once it handled all the previous messages.
+    #[serde(skip)]
+    Shutdown,
+}
+
+/// The arguments of a `:s/pattern/replacement/flags` command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Substitution {
+    /// An empty pattern stands for the last search.
+    pub pattern: String,
+    pub replacement: String,
+    /// Apply on the whole file (`:%s`) instead of the current line.
+    pub whole_file: bool,
+    /// Replace all the matches of a line instead of the first one.
+    pub global: bool,
+    pub case_sensitive: bool,
+    pub regex: bool,
+}
+
+/// A message read by the EventController.
+pub enum Message {
+    Core(String),
+    Client(ClientMessage),
+}
 
 /// Wraps an instance of `mpsc::Sender`, implementing `Write`.
 ///
 /// This lets the tx side of an mpsc::channel serve as the destination
 /// stream for an RPC loop.
-pub struct Writer(Sender<String>);
+#[derive(Clone)]
+pub struct Writer {
+    sender: Sender<Message>,
+    /// Record the written messages as going in the given direction.
+    recorder: Option<(Recorder, Direction)>,
+}
+
+impl Writer {
+    fn new(sender: Sender<Message>) -> Self {
+        Self {
+            sender,
+            recorder: None,
+        }
+    }
+
+    fn recorded(self, recorder: Option<&Recorder>, direction: Direction) -> Self {
+        Self {
+            recorder: recorder.map(|recorder| (recorder.clone(), direction)),
+            ..self
+        }
+    }
+}
 
 impl Write for Writer {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let s = String::from_utf8(buf.to_vec()).unwrap();
-        self.0
-            .send(s)
+        if let Some((ref recorder, direction)) = self.recorder {
+            recorder.record(direction, &s);
+        }
+
+        self.sender
+            .send(Message::Core(s))
             .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("{:?}", err)))
             .map(|_| buf.len())
     }
@@ -28,7 +148,71 @@ impl Write for Writer {
 
 /// Wraps an instance of `mpsc::Receiver`, providing convenience methods
 /// for parsing received messages.
-pub struct Reader(Receiver<String>);
+pub struct Reader {
+    receiver: Receiver<Message>,
+    /// Where the client messages go, in the order of their notifications.
+    client_messages: Option<Sender<ClientMessage>>,
+    /// Record the messages in the order they are read, whatever the order
+    /// of their writers.
+    recorder: Option<Recorder>,
+}
+
+impl Reader {
+    fn new(receiver: Receiver<Message>) -> Self {
+        Self {
+            receiver,
+            client_messages: None,
+            recorder: None,
+        }
+    }
+
+    fn recorded(self, recorder: Option<&Recorder>) -> Self {
+        Self {
+            recorder: recorder.cloned(),
+            ..self
+        }
+    }
+
+    fn record(&self, message: &Message) {
+        let recorder = match self.recorder {
+            Some(ref recorder) => recorder,
+            None => return,
+        };
+
+        match message {
+            Message::Core(raw) => recorder.record(Direction::FromCore, raw),
+            // The shutdown has no JSON form.
+            Message::Client(message) => {
+                if let Ok(raw) = serde_json::to_string(message) {
+                    recorder.record(Direction::Client, &raw);
+                }
+            }
+        }
+    }
+
+    /// Take the client messages out of the stream read by the RPC loop.
+    ///
+    /// Each client message is given to the returned channel and replaced by
+    /// a "client_message" notification inside the stream. Without it, the
+    /// client messages are dropped.
+    pub fn client_messages(&mut self) -> Receiver<ClientMessage> {
+        let (sender, receiver) = channel();
+        self.client_messages = Some(sender);
+
+        receiver
+    }
+}
+
+/// Give a reader of the given messages, which ends after the last one.
+pub fn reader_of(messages: Vec<Message>) -> Reader {
+    let (sender, receiver) = channel();
+    for message in messages {
+        // The receiver is still there.
+        sender.send(message).unwrap();
+    }
+
+    Reader::new(receiver)
+}
 
 impl Read for Reader {
     fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
@@ -48,74 +232,397 @@ impl BufRead for Reader {
     }
 
     fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
-        let event = match self.0.recv() {
-            Ok(s) => s,
-            Err(_) => return Ok(0),
-        };
+        let event = loop {
+            let message = self.receiver.recv();
+            if let Ok(ref message) = message {
+                self.record(message);
+            }
 
-        if &event == r#"{"method":"command","params":{"method":"exit"}}"# {
-            // It receive a close commmand from the writer indicating the chan
-            // should be closes. The event is sent by the InputController when
-            // the user ask to quit the program.
-            //
-            // This method is required because the chan producers a shared between
-            // The InputController and the EventController threads and it's
-            // impossible for the InputController to close the EventController
-            // channel.
-            //
-            // When the Reader receives the command, it close the channel between
-            // the InputController which lead to the following steps in order:
-            // - The channel between the the InputController and the Core close itself.
-            // - The Core event loop stop itself safely.
-            // - The channel between the Core and the EventController close itself.
-            // - The the EventController event loop stop itself safely.
-            // - The main exit.
-            return Ok(0);
-        }
+            match message {
+                Ok(Message::Core(s)) => break s,
+                // The end of the input stops the event loop reading it. The
+                // channel can't be closed by the InputController because the
+                // core owns a writer on it.
+                Ok(Message::Client(ClientMessage::Shutdown)) | Err(_) => return Ok(0),
+                // The RPC loop only notifies the EventController, which
+                // receives the message itself from its own channel.
+                Ok(Message::Client(message)) => match self.client_messages {
+                    Some(ref sender) if sender.send(message).is_ok() => {
+                        break json!({ "method": CLIENT_MESSAGE_METHOD, "params": {} }).to_string();
+                    }
+                    _ => warn!("client message dropped without any receiver"),
+                },
+            }
+        };
 
         buf.push_str(&event);
         Ok(event.len())
     }
 }
 
-pub struct ClientToClientWriter(Writer);
+#[derive(Clone)]
+pub struct ClientToClientWriter {
+    sender: Sender<Message>,
+}
 
 impl ClientToClientWriter {
-    pub fn send_rpc_notification(&mut self, method: &str, params: &Value) {
-        let raw_content = match serde_json::to_vec(&json!({"method": method, "params": params})) {
-            Ok(raw) => raw,
-            Err(err) => {
-                error!("failed to create the notification {}: {}", method, err);
-                return;
-            }
-        };
-
-        match self.0.write(&raw_content) {
-            Ok(_) => (),
-            Err(err) => error!("failed to send the notification {}: {}", method, err),
-        };
+    pub fn send(&mut self, message: ClientMessage) {
+        if let Err(err) = self.sender.send(Message::Client(message)) {
+            error!("failed to send the client message: {:?}", err.0);
+        }
     }
 }
 
-pub fn start_xi_core() -> (Writer, Reader, ClientToClientWriter) {
-    let mut core = XiCore::new();
+/// Give a channel between the clients without any core, for the tests.
+#[cfg(test)]
+pub fn client_channel() -> (ClientToClientWriter, Reader) {
+    let (sender, receiver) = channel();
+    let writer = ClientToClientWriter { sender };
 
-    let (to_core_tx, to_core_rx) = channel();
-    let client_to_core_writer = Writer(to_core_tx);
-    let client_to_core_reader = Reader(to_core_rx);
+    (writer, Reader::new(receiver))
+}
+
+/// The settings of the `[core]` section of `config.toml`.
+#[derive(Debug, Default, Deserialize)]
+pub struct CoreConfig {
+    /// The xi-core binary to spawn instead of the embedded core.
+    pub path: Option<PathBuf>,
+}
+
+/// How the client talks to xi-core.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transport {
+    /// Run the core linked into vixi inside a thread.
+    Embedded,
+    /// Spawn an external xi-core binary and speak JSON-RPC over its stdin and
+    /// stdout.
+    Process(PathBuf),
+    /// Connect to the core shared by a `vixi --server` over the Unix socket
+    /// at the given path.
+    Socket(PathBuf),
+}
 
+/// Start the core with the given transport.
+///
+/// Whatever the transport, the messages go through the same channels: the
+/// client writes into the returned `Writer` and reads the core messages,
+/// mixed with the client to client ones, from the returned `Reader`.
+///
+/// The returned thread stops once the core received all the messages of the
+/// client, after all the clones of the `Writer` have been dropped.
+///
+/// The messages sent to the core and the ones read from the `Reader` are
+/// given to the `recorder`, if any.
+pub fn start_xi_core(
+    transport: &Transport,
+    recorder: Option<&Recorder>,
+) -> Result<(Writer, Reader, ClientToClientWriter, JoinHandle<()>), Error> {
     let (from_core_tx, from_core_rx) = channel();
-    let core_to_client_writer = Writer(from_core_tx.clone());
-    let core_to_client_reader = Reader(from_core_rx);
+    let core_to_client_writer = Writer::new(from_core_tx.clone());
+    let core_to_client_reader = Reader::new(from_core_rx).recorded(recorder);
 
-    let client_to_client_writer = ClientToClientWriter(Writer(from_core_tx));
+    let client_to_client_writer = ClientToClientWriter {
+        sender: from_core_tx,
+    };
 
-    let mut core_event_loop = RpcLoop::new(core_to_client_writer);
-    thread::spawn(move || core_event_loop.mainloop(|| client_to_core_reader, &mut core));
+    let (client_to_core_writer, core_thread) = match transport {
+        Transport::Embedded => start_embedded_core(core_to_client_writer),
+        Transport::Process(path) => spawn_core_process(path, core_to_client_writer)?,
+        Transport::Socket(path) => connect_to_server(path, core_to_client_writer)?,
+    };
 
-    (
-        client_to_core_writer,
+    Ok((
+        client_to_core_writer.recorded(recorder, Direction::ToCore),
         core_to_client_reader,
         client_to_client_writer,
-    )
+        core_thread,
+    ))
+}
+
+/// Start an embedded core without any client attached, for the server mode.
+///
+/// The messages of the core are read from the returned `Reader`, one by line.
+pub fn start_headless_core() -> (Writer, Reader) {
+    let (from_core_tx, from_core_rx) = channel();
+    let (client_to_core_writer, _) = start_embedded_core(Writer::new(from_core_tx));
+
+    (client_to_core_writer, Reader::new(from_core_rx))
+}
+
+fn start_embedded_core(core_to_client_writer: Writer) -> (Writer, JoinHandle<()>) {
+    let mut core = XiCore::new();
+
+    let (to_core_tx, to_core_rx) = channel();
+    let client_to_core_writer = Writer::new(to_core_tx);
+    let client_to_core_reader = Reader::new(to_core_rx);
+
+    let mut core_event_loop = RpcLoop::new(core_to_client_writer);
+    let core_thread = thread::spawn(move || {
+        match core_event_loop.mainloop(|| client_to_core_reader, &mut core) {
+            Ok(()) | Err(ReadError::Disconnect) => (),
+            Err(err) => error!("the core stopped: {:?}", err),
+        }
+    });
+
+    (client_to_core_writer, core_thread)
+}
+
+/// Spawn the xi-core binary at `path` and plug its stdin and stdout to the
+/// channels of the client.
+///
+/// The core stops by itself once all the client writers are dropped: its
+/// stdin is closed and it reaches the end of its input. The returned thread
+/// waits for it.
+fn spawn_core_process(
+    path: &Path,
+    core_to_client_writer: Writer,
+) -> Result<(Writer, JoinHandle<()>), Error> {
+    let mut child = Command::new(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format_err!("failed to start {}: {}", path.display(), err))?;
+
+    // The pipes are always set by `Stdio::piped`.
+    let stdin = child.stdin.take().unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+
+    // The core would scramble the screen by writing on the terminal.
+    let core_thread = thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().filter_map(Result::ok) {
+            warn!("xi-core: {}", line);
+        }
+
+        match child.wait() {
+            Ok(status) => info!("xi-core exited with {}", status),
+            Err(err) => error!("failed to wait for xi-core: {}", err),
+        }
+    });
+
+    let (client_to_core_writer, _) = plug_stream(stdin, stdout, core_to_client_writer);
+
+    Ok((client_to_core_writer, core_thread))
+}
+
+/// Connect to the Unix socket of a `vixi --server` and plug it to the
+/// channels of the client.
+///
+/// The shared core outlives the client, the returned thread only waits for
+/// the messages to be written into the socket.
+fn connect_to_server(
+    path: &Path,
+    core_to_client_writer: Writer,
+) -> Result<(Writer, JoinHandle<()>), Error> {
+    let stream = UnixStream::connect(path)
+        .map_err(|err| format_err!("failed to connect to {}: {}", path.display(), err))?;
+    let input = stream.try_clone()?;
+
+    Ok(plug_stream(input, stream, core_to_client_writer))
+}
+
+/// Plug a core speaking JSON-RPC over a byte stream, one message per line, to
+/// the channels of the client.
+///
+/// The messages written into the returned `Writer` are copied into `input`
+/// by the returned thread. It closes `input` once all the clones of the
+/// `Writer` are dropped. The lines read from `output` are given to
+/// `core_to_client_writer`.
+fn plug_stream<W, R>(
+    mut input: W,
+    output: R,
+    mut core_to_client_writer: Writer,
+) -> (Writer, JoinHandle<()>)
+where
+    W: Write + Send + 'static,
+    R: Read + Send + 'static,
+{
+    let (to_core_tx, to_core_rx) = channel();
+
+    let input_thread = thread::spawn(move || {
+        for message in to_core_rx {
+            // Only the core messages are written into a `Writer`, each one
+            // already ends with a new line.
+            let raw = match message {
+                Message::Core(raw) => raw,
+                Message::Client(_) => continue,
+            };
+
+            if let Err(err) = input.write_all(raw.as_bytes()).and_then(|_| input.flush()) {
+                error!("failed to write to xi-core: {}", err);
+                break;
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        for line in BufReader::new(output).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => {
+                    error!("failed to read from xi-core: {}", err);
+                    break;
+                }
+            };
+
+            if core_to_client_writer.write_all(line.as_bytes()).is_err() {
+                // The client is gone.
+                break;
+            }
+        }
+    });
+
+    (Writer::new(to_core_tx), input_thread)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        plug_stream, ClientMessage, ClientToClientWriter, Message, Reader, Recorder, Writer,
+    };
+    use serde_json::Value;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::os::unix::io::FromRawFd;
+    use std::path::Path;
+    use std::process;
+    use std::sync::mpsc::channel;
+
+    /// Give the read and the write ends of a new pipe.
+    fn pipe() -> (File, File) {
+        let mut fds = [0; 2];
+        assert_eq!(0, unsafe { libc::pipe(fds.as_mut_ptr()) });
+
+        unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) }
+    }
+
+    /// Give the method of each message of the recording at `path`.
+    fn recorded_methods(path: &Path) -> Vec<String> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let record: Value = serde_json::from_str(line).unwrap();
+                record["message"]["method"].as_str().unwrap().to_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_plug_stream() {
+        let (core_stdin, client_output) = pipe();
+        let (client_input, mut core_stdout) = pipe();
+        let (tx, rx) = channel();
+        let mut reader = Reader::new(rx);
+        let (mut writer, input_thread) = plug_stream(client_output, client_input, Writer::new(tx));
+
+        // The messages of the client are copied to the core.
+        let mut core_stdin = BufReader::new(core_stdin);
+        writer.write_all(b"{\"method\":\"ping\"}\n").unwrap();
+        let mut line = String::new();
+        core_stdin.read_line(&mut line).unwrap();
+        assert_eq!("{\"method\":\"ping\"}\n", line);
+
+        // The lines of the core are given to the client one by one.
+        core_stdout
+            .write_all(b"{\"method\":\"pong\"}\n{}\n")
+            .unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!("{\"method\":\"pong\"}", line);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!("{}", line);
+
+        // Dropping the writer closes the input of the core.
+        drop(writer);
+        input_thread.join().unwrap();
+        let mut rest = String::new();
+        core_stdin.read_to_string(&mut rest).unwrap();
+        assert_eq!("", rest);
+
+        // The end of the output of the core closes the channel.
+        drop(core_stdout);
+        assert_eq!(0, reader.read_line(&mut line).unwrap());
+    }
+
+    #[test]
+    fn test_reader_stops_on_shutdown() {
+        let (tx, rx) = channel();
+        let mut reader = Reader::new(rx);
+        let client_messages = reader.client_messages();
+        let mut core_writer = Writer::new(tx.clone());
+        let mut client_writer = ClientToClientWriter { sender: tx };
+
+        client_writer.send(ClientMessage::Alert {
+            msg: String::from("hello"),
+        });
+        client_writer.send(ClientMessage::Shutdown);
+        core_writer.write_all(b"{\"method\":\"update\"}").unwrap();
+
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let notification: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            json!({"method": "client_message", "params": {}}),
+            notification
+        );
+        assert_eq!(
+            Ok(ClientMessage::Alert {
+                msg: String::from("hello")
+            }),
+            client_messages.try_recv()
+        );
+
+        // The messages sent after the shutdown are never read.
+        assert_eq!(0, reader.read_line(&mut line).unwrap());
+    }
+
+    #[test]
+    fn test_reader_gives_the_core_messages() {
+        let (tx, rx) = channel();
+        let mut reader = Reader::new(rx);
+        tx.send(Message::Core(String::from("{}\n"))).unwrap();
+        drop(tx);
+
+        let mut line = String::new();
+        assert_eq!(3, reader.read_line(&mut line).unwrap());
+        assert_eq!("{}\n", line);
+        assert_eq!(0, reader.read_line(&mut line).unwrap());
+    }
+
+    #[test]
+    fn test_record_the_messages_in_their_reading_order() {
+        let path = env::temp_dir().join(format!("vixi-test-{}.jsonl", process::id()));
+        let recorder = Recorder::create(&path, 80, 24).unwrap();
+
+        let (tx, rx) = channel();
+        let mut reader = Reader::new(rx).recorded(Some(&recorder));
+        let _client_messages = reader.client_messages();
+        let mut core_writer = Writer::new(tx.clone());
+        let mut client_writer = ClientToClientWriter { sender: tx };
+
+        core_writer.write_all(b"{\"method\":\"update\"}").unwrap();
+        client_writer.send(ClientMessage::HideCommandLine {});
+        core_writer
+            .write_all(b"{\"method\":\"scroll_to\"}")
+            .unwrap();
+
+        // The messages are recorded once they are read, not once they are
+        // written.
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(vec!["resize", "update"], recorded_methods(&path));
+
+        reader.read_line(&mut line).unwrap();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(
+            vec!["resize", "update", "hide_command_line", "scroll_to"],
+            recorded_methods(&path)
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
 }
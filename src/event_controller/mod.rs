@@ -1,6 +1,9 @@
+mod sign;
 mod status_bar;
 pub mod style;
+mod tab_line;
 pub mod view;
+mod width;
 pub mod window;
 
 pub use self::style::Styles;
@@ -8,12 +11,19 @@ pub use self::style::Styles;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
+use std::sync::mpsc::Receiver;
 
+use self::sign::SignColumn;
 use self::status_bar::StatusBar;
 use self::style::{RGBColor, StyleID};
-use self::view::{View, ViewID};
+use self::tab_line::TabLine;
+use self::view::{LineNumbers, View, ViewID, Wrap};
 use self::window::Layout;
+use crate::core::{ClientMessage, Substitution, CLIENT_MESSAGE_METHOD};
+use crate::split::SplitTree;
+use crate::state::SharedState;
 
+use regex::Regex;
 use serde_json::Value;
 use xi_rpc::{RemoteError, RpcCall, RpcCtx};
 
@@ -34,12 +44,13 @@ pub struct Operation {
     lines: Option<Vec<LineDescription>>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, PartialEq)]
 pub struct Annotation {
     #[serde(rename = "type")]
     kind: String,
     n: usize,
-    payloads: Option<()>,
+    payloads: Option<Value>,
+    /// The annotated ranges as `[start_line, start_col, end_line, end_col]`.
     ranges: Vec<[usize; 4]>,
 }
 
@@ -61,12 +72,61 @@ pub struct Line {
     pub is_dirty: bool,
 }
 
+/// A search query of the core, as given by "find_status".
+#[derive(Deserialize, Debug, Clone)]
+struct FindQuery {
+    chars: Option<String>,
+    #[serde(default)]
+    case_sensitive: bool,
+    #[serde(default)]
+    is_regex: bool,
+    #[serde(default)]
+    whole_words: bool,
+    matches: usize,
+    /// The line of each match, starting at 1.
+    #[serde(default)]
+    lines: Vec<usize>,
+}
+
+/// A substitution waiting for the core to find the matches of its pattern
+/// and to take its replacement.
+struct PendingSubstitution {
+    view_id: String,
+    pattern: String,
+    replacement: String,
+    /// The line whose matches are replaced, `None` for the whole file.
+    line: Option<usize>,
+    /// Replace all the matches of a line instead of the first one.
+    global: bool,
+    /// The matches of the pattern, given by the "find_status" answering the
+    /// find query of the substitution.
+    query: Option<FindQuery>,
+}
+
 pub struct EventController {
     styles: Rc<RefCell<Box<dyn Styles>>>,
     views: HashMap<ViewID, View>,
     layout: Box<dyn Layout>,
     status_bar: StatusBar,
+    tab_line: TabLine,
+    /// The view of the focused pane.
     current_view: String,
+    /// The panes of the screen with the views they display.
+    panes: SplitTree,
+    /// The soft wrap settings shared by all the views.
+    wrap: Wrap,
+    /// The numbers displayed inside the gutter of all the views.
+    line_numbers: LineNumbers,
+    /// When the sign column is displayed inside the views.
+    sign_column: SignColumn,
+    state: SharedState,
+    /// The messages of the InputController, one for each "client_message"
+    /// notification.
+    client_messages: Receiver<ClientMessage>,
+    /// The last search of the user, given back to the core after the
+    /// substitutions.
+    search: Option<FindQuery>,
+    pending_substitution: Option<PendingSubstitution>,
 }
 
 impl xi_rpc::Handler for EventController {
@@ -76,103 +136,501 @@ impl xi_rpc::Handler for EventController {
     fn handle_notification(&mut self, ctx: &RpcCtx, rpc: Self::Notification) {
         debug!("handle notification: {}", rpc.method.as_str());
         match rpc.method.as_str() {
-            "add_status_item" => self.handle_new_status_item(&rpc.params),
-            "update_status_item" => self.update_status_item(&rpc.params),
+            "add_status_item" | "update_status_item" => self.handle_status_item(&rpc.params),
             "plugin_started" => debug!("{}: -> {}", &rpc.method, &rpc.params),
             "available_languages" => debug!("{}", &rpc.method),
             "available_themes" => debug!("{}", &rpc.method),
             "available_plugins" => debug!("{}", &rpc.method),
-            "config_changed" => debug!("{}", &rpc.method),
+            "config_changed" => self.handle_config_change(&ctx, &rpc.params),
             "def_style" => self.handle_style_change(&rpc.params),
             "language_changed" => debug!("{}", &rpc.method),
             "scroll_to" => self.handle_cursor_move(&ctx, &rpc.params),
             "update" => self.handle_content_update(&ctx, &rpc.params),
             "theme_changed" => debug!("{}", &rpc.method),
-            "set_path_for_view" => self.set_path_for_view(&ctx, &rpc.params),
-            "write_to_file" => self.write_to_file(&ctx, &rpc.params),
+            "alert" => self.handle_alert(&rpc.params),
+            "find_status" => self.handle_find_status(&rpc.params),
+            "replace_status" => self.handle_replace_status(&ctx, &rpc.params),
+            CLIENT_MESSAGE_METHOD => match self.client_messages.try_recv() {
+                Ok(message) => self.handle_client_message(&ctx, message),
+                Err(err) => error!("no client message to handle: {}", err),
+            },
             _ => warn!("unhandled notif \"{}\" -> {}", &rpc.method, &rpc.params),
         };
+
+        // The views move the cursor when they are redrawn, give it back to
+        // the command line being edited or to the focused view.
+        if self.status_bar.has_prompt() {
+            self.status_bar.focus_prompt();
+        } else if let Some(view) = self.views.get(&self.current_view) {
+            view.refresh_cursor();
+        }
     }
 
     fn handle_request(&mut self, _ctx: &RpcCtx, rpc: Self::Request) -> Result<Value, RemoteError> {
-        info!("[request] {} -> {:#?}", rpc.method, rpc.params);
-        Ok(json!({}))
+        match rpc.method.as_str() {
+            "measure_width" => {
+                // The request doesn't give its view, the tabs are expanded
+                // like inside the focused one.
+                let tab_size = self
+                    .views
+                    .get(&self.current_view)
+                    .map_or(width::DEFAULT_TAB_SIZE, View::tab_size);
+                measure_width(&rpc.params, tab_size)
+            }
+            _ => {
+                info!("[request] {} -> {:#?}", rpc.method, rpc.params);
+                Ok(json!({}))
+            }
+        }
     }
 }
 
 impl EventController {
-    pub fn new(layout: Box<dyn Layout>, styles: Rc<RefCell<Box<dyn Styles>>>) -> Self {
+    pub fn new(
+        layout: Box<dyn Layout>,
+        styles: Rc<RefCell<Box<dyn Styles>>>,
+        state: SharedState,
+        client_messages: Receiver<ClientMessage>,
+    ) -> Self {
         let status_bar = StatusBar::new(layout.create_new_status_bar_window());
+        let tab_line = TabLine::new(layout.create_tab_line_window(), styles.clone());
 
         Self {
             styles,
             layout,
             views: HashMap::new(),
             status_bar,
+            tab_line,
             current_view: String::new(),
+            panes: SplitTree::new(""),
+            wrap: Wrap::default(),
+            line_numbers: LineNumbers::Absolute,
+            sign_column: SignColumn::Auto,
+            state,
+            client_messages,
+            search: None,
+            pending_substitution: None,
+        }
+    }
+
+    /// Handle a message sent by the InputController.
+    fn handle_client_message(&mut self, ctx: &RpcCtx, message: ClientMessage) {
+        match message {
+            ClientMessage::SetPathForView { view_id, path } => {
+                self.set_path_for_view(ctx, &view_id, &path)
+            }
+            ClientMessage::SetLayout {
+                tabs,
+                current_tab,
+                panes,
+                focused_view,
+            } => self.set_layout(ctx, tabs, current_tab, panes, focused_view),
+            ClientMessage::RemoveView { view_id } => self.remove_view(&view_id),
+            ClientMessage::Resize { width, height } => self.handle_resize(ctx, width, height),
+            ClientMessage::SetOption { key, value } => self.set_option(&key, &value),
+            ClientMessage::WriteToFile { view_id, file_path } => {
+                self.write_to_file(ctx, &view_id, file_path.as_deref())
+            }
+            ClientMessage::Substitute {
+                view_id,
+                substitution,
+            } => self.substitute(ctx, &view_id, &substitution),
+            ClientMessage::ShowCommandLine {
+                prompt,
+                content,
+                cursor,
+            } => self.status_bar.show_prompt(&prompt, &content, cursor),
+            ClientMessage::HideCommandLine {} => self.hide_command_line(),
+            ClientMessage::Alert { msg } => self.status_bar.show_message(&msg),
+            ClientMessage::AddStatusItem { key, value, .. }
+            | ClientMessage::UpdateStatusItem { key, value } => {
+                self.update_status_item(&key, &value)
+            }
+            // The reader stops on the shutdown, it never gives it.
+            ClientMessage::Shutdown => (),
+        }
+    }
+
+    fn set_path_for_view(&mut self, ctx: &RpcCtx, view_id: &str, path: &str) {
+        self.create_view_if_required(ctx, view_id);
+        self.views.get_mut(view_id).unwrap().set_file_path(path);
+    }
+
+    /// Handle the "set_layout" message.
+    ///
+    /// It gives the tab pages, the panes of the current one, the view
+    /// displayed by each pane and the focused one.
+    fn set_layout(
+        &mut self,
+        ctx: &RpcCtx,
+        tabs: Vec<String>,
+        current_tab: usize,
+        panes: SplitTree,
+        focused_view: String,
+    ) {
+        if focused_view != self.current_view {
+            // The search results belong to the previous view.
+            self.status_bar.update_search_status("");
+        }
+
+        self.tab_line.update(tabs, current_tab);
+        self.layout.set_tab_line_visible(self.tab_line.is_visible());
+
+        self.panes = panes;
+        self.current_view = focused_view;
+        self.draw_panes(ctx);
+    }
+
+    /// Handle the "resize" message, sent when the terminal is resized.
+    ///
+    /// Every window is created again with the new size and the views give
+    /// their new size to the core.
+    fn handle_resize(&mut self, ctx: &RpcCtx, width: u32, height: u32) {
+        self.layout.resize(width, height);
+
+        self.status_bar
+            .set_window(self.layout.create_new_status_bar_window());
+        self.tab_line
+            .set_window(self.layout.create_tab_line_window());
+        self.draw_panes(ctx);
+    }
+
+    /// Display each view of the panes inside its own window.
+    fn draw_panes(&mut self, ctx: &RpcCtx) {
+        for (view_id, view) in self.views.iter_mut() {
+            if !self.panes.contains(view_id) {
+                view.hide();
+            }
+        }
+
+        for (view_id, window) in self.layout.create_view_windows(&self.panes) {
+            self.create_view_if_required(ctx, &view_id);
+            self.views.get_mut(&view_id).unwrap().show(ctx, window);
         }
     }
 
-    fn set_path_for_view(&mut self, ctx: &RpcCtx, body: &Value) {
+    /// Handle the "remove_view" message, sent once a view is closed.
+    fn remove_view(&mut self, view_id: &str) {
+        self.views.remove(view_id);
+        self.state.lock().unwrap().unsaved_views.remove(view_id);
+    }
+
+    fn write_to_file(&mut self, ctx: &RpcCtx, view_id: &str, file_path: Option<&str>) {
+        let view = match self.views.get_mut(view_id) {
+            Some(view) => view,
+            None => return,
+        };
+
+        if let Some(file_path) = file_path {
+            view.set_file_path(file_path);
+        }
+
+        if !view.write_to_file(ctx) {
+            self.status_bar.show_message("No file name");
+        }
+    }
+
+    fn hide_command_line(&mut self) {
+        self.status_bar.hide_prompt();
+
+        if let Some(view) = self.views.get(&self.current_view) {
+            view.refresh_cursor();
+        }
+    }
+
+    /// Handle the "set_option" message, sent by a `:set` command changing
+    /// an option of the client instead of the core.
+    fn set_option(&mut self, key: &str, value: &Value) {
+        match (key, value) {
+            ("wrap", Value::Bool(enabled)) => self.wrap.enabled = *enabled,
+            ("nowrap", Value::Bool(disabled)) => self.wrap.enabled = !*disabled,
+            ("wrap_column", Value::Number(column)) if column.is_u64() => {
+                self.wrap.column = match column.as_u64() {
+                    Some(0) | None => None,
+                    Some(column) => Some(column as usize),
+                }
+            }
+            ("line_numbers", Value::String(name)) if LineNumbers::from_name(name).is_some() => {
+                self.line_numbers = LineNumbers::from_name(name).unwrap();
+            }
+            ("sign_column", Value::String(name)) if SignColumn::from_name(name).is_some() => {
+                self.sign_column = SignColumn::from_name(name).unwrap();
+            }
+            _ => {
+                self.status_bar
+                    .show_message(&format!("Invalid argument: {}={}", key, value));
+                return;
+            }
+        }
+
+        // Each view is redrawn once, with the changed option only.
+        for view in self.views.values_mut() {
+            match key {
+                "line_numbers" => view.set_line_numbers(self.line_numbers),
+                "sign_column" => view.set_sign_column(self.sign_column),
+                _ => view.set_wrap(self.wrap),
+            }
+        }
+    }
+
+    /// Handle the "alert" event of the core.
+    ///
+    /// The command line errors come as "alert" messages of the client.
+    fn handle_alert(&mut self, body: &Value) {
         #[derive(Deserialize, Debug)]
         struct Event {
-            view_id: String,
-            path: String,
+            msg: String,
         }
 
         let event: Event = serde_json::from_value(body.clone()).unwrap();
 
-        self.create_view_if_required(ctx, &event.view_id);
-        self.views
-            .get_mut(&event.view_id)
-            .unwrap()
-            .set_file_path(&event.path);
+        self.status_bar.show_message(&event.msg);
     }
 
-    fn write_to_file(&mut self, ctx: &RpcCtx, body: &Value) {
+    /// Handle the "add_status_item" and "update_status_item" events of the
+    /// plugins.
+    fn handle_status_item(&mut self, body: &Value) {
         #[derive(Deserialize, Debug)]
         struct Event {
-            view_id: String,
+            key: String,
+            value: String,
         }
 
         let event: Event = serde_json::from_value(body.clone()).unwrap();
 
-        self.views
-            .get_mut(&event.view_id)
-            .unwrap()
-            .write_to_file(ctx);
+        self.update_status_item(&event.key, &event.value);
+    }
+
+    /// Handle the "add_status_item" and "update_status_item" messages.
+    fn update_status_item(&mut self, key: &str, value: &str) {
+        match key {
+            "change-mode" => self.status_bar.update_mode(value),
+            "pending-keys" => self.status_bar.update_pending_keys(value),
+            _ => warn!("unknown status item: {}", key),
+        }
     }
 
-    fn handle_new_status_item(&mut self, body: &Value) {
+    /// Handle the "find_status" event.
+    ///
+    /// It displays the number of matches of the current search, or keeps the
+    /// matches of the substitution in progress.
+    fn handle_find_status(&mut self, body: &Value) {
         #[derive(Deserialize, Debug)]
         struct Event {
-            //source: String,
-            key: String,
-            value: String,
-            alignment: String,
+            view_id: String,
+            queries: Vec<FindQuery>,
         }
 
         let event: Event = serde_json::from_value(body.clone()).unwrap();
 
-        if let "change-mode" = event.key.as_str() {
-            self.status_bar.update_mode(&event.value);
+        if event.view_id != self.current_view {
+            return;
+        }
+
+        let query = event.queries.into_iter().next();
+
+        // The find query of a substitution is not a search of the user.
+        if let Some(ref mut pending) = self.pending_substitution {
+            let chars = query.as_ref().and_then(|query| query.chars.as_ref());
+            if pending.view_id == event.view_id && chars == Some(&pending.pattern) {
+                pending.query = query;
+                return;
+            }
         }
+
+        // An empty query removes the search.
+        self.search =
+            query.filter(|query| query.chars.as_ref().map(String::is_empty) == Some(false));
+
+        let status = match self.search {
+            Some(FindQuery {
+                chars: Some(ref chars),
+                matches,
+                ..
+            }) => match matches {
+                0 => format!("Pattern not found: {}", chars),
+                1 => String::from("1 match"),
+                n => format!("{} matches", n),
+            },
+            _ => String::new(),
+        };
+
+        self.status_bar.update_search_status(&status);
     }
 
-    fn update_status_item(&mut self, body: &Value) {
+    /// Handle the "replace_status" event.
+    ///
+    /// Once the core took the replacement of the substitution in progress,
+    /// the matches given by its find query are replaced by the core and
+    /// counted inside the status bar.
+    fn handle_replace_status(&mut self, ctx: &RpcCtx, body: &Value) {
+        #[derive(Deserialize, Debug)]
+        struct Status {
+            chars: String,
+        }
+
         #[derive(Deserialize, Debug)]
         struct Event {
-            key: String,
-            value: String,
+            view_id: String,
+            status: Status,
         }
 
         let event: Event = serde_json::from_value(body.clone()).unwrap();
 
-        if let "change-mode" = event.key.as_str() {
-            self.status_bar.update_mode(&event.value);
+        let pending = match self.pending_substitution.take() {
+            Some(pending)
+                if pending.view_id == event.view_id
+                    && pending.replacement == event.status.chars =>
+            {
+                pending
+            }
+            pending => {
+                self.pending_substitution = pending;
+                return;
+            }
+        };
+
+        let query = match pending.query {
+            Some(query) => query,
+            None => {
+                warn!(
+                    "no matches given for the substitution of {}",
+                    pending.pattern
+                );
+                self.report_substitutions(0);
+                self.restore_search(ctx, &event.view_id);
+                return;
+            }
+        };
+
+        let count = match pending.line {
+            None if pending.global => {
+                if query.matches > 0 {
+                    ctx.get_peer().send_rpc_notification(
+                        "edit",
+                        &json!({ "method": "replace_all", "view_id": event.view_id }),
+                    );
+                }
+                query.matches
+            }
+            None => {
+                let mut lines = query.lines;
+                lines.dedup();
+
+                // Start from the end of the file in order to keep the
+                // previous lines at the same place.
+                for line in lines.iter().rev() {
+                    replace_in_line(ctx, &event.view_id, line.saturating_sub(1), 1);
+                }
+                lines.len()
+            }
+            Some(line) => {
+                let matches = query.lines.iter().filter(|&&ln| ln == line + 1).count();
+                let count = if pending.global {
+                    matches
+                } else {
+                    matches.min(1)
+                };
+
+                replace_in_line(ctx, &event.view_id, line, count);
+                count
+            }
+        };
+
+        self.report_substitutions(count);
+        self.restore_search(ctx, &event.view_id);
+    }
+
+    fn report_substitutions(&mut self, count: usize) {
+        match count {
+            0 => self.status_bar.show_message("Pattern not found"),
+            1 => self.status_bar.show_message("1 substitution"),
+            n => self
+                .status_bar
+                .show_message(&format!("{} substitutions", n)),
         }
     }
 
+    /// Handle the "substitute" message sent by the input controller.
+    ///
+    /// The pattern and the replacement are given to the core, which answers
+    /// with the lines of the matches then with the replacement. The matches
+    /// are replaced once both are known.
+    fn substitute(&mut self, ctx: &RpcCtx, view_id: &str, event: &Substitution) {
+        if event.regex {
+            if let Err(err) = Regex::new(&event.pattern) {
+                self.status_bar
+                    .show_message(&format!("Invalid pattern: {}", err));
+                return;
+            }
+        }
+
+        let line = match self.views.get(view_id) {
+            Some(_) if event.whole_file => None,
+            Some(view) => Some(view.cursor_line()),
+            None => return,
+        };
+
+        let peer = ctx.get_peer();
+        peer.send_rpc_notification(
+            "edit",
+            &json!({
+                "method": "find",
+                "view_id": view_id,
+                "params": {
+                    "chars": event.pattern,
+                    "case_sensitive": event.case_sensitive,
+                    "regex": event.regex,
+                    "whole_words": false,
+                }
+            }),
+        );
+        peer.send_rpc_notification(
+            "edit",
+            &json!({
+                "method": "replace",
+                "view_id": view_id,
+                "params": {
+                    "chars": event.replacement,
+                    "preserve_case": false,
+                }
+            }),
+        );
+
+        self.pending_substitution = Some(PendingSubstitution {
+            view_id: view_id.to_owned(),
+            pattern: event.pattern.clone(),
+            replacement: event.replacement.clone(),
+            line,
+            global: event.global,
+            query: None,
+        });
+    }
+
+    /// Give back the last search of the user to the core, replaced by the
+    /// find query of a substitution.
+    fn restore_search(&self, ctx: &RpcCtx, view_id: &str) {
+        let params = match self.search {
+            Some(ref query) => json!({
+                "chars": query.chars,
+                "case_sensitive": query.case_sensitive,
+                "regex": query.is_regex,
+                "whole_words": query.whole_words,
+            }),
+            None => json!({
+                "chars": None::<String>,
+                "case_sensitive": true,
+                "regex": false,
+                "whole_words": false,
+            }),
+        };
+
+        ctx.get_peer().send_rpc_notification(
+            "edit",
+            &json!({ "method": "find", "view_id": view_id, "params": params }),
+        );
+    }
+
     /// Handle the "def_style" event.
     ///
     /// This function need to create a new set of background/foreground and save
@@ -213,6 +671,33 @@ impl EventController {
             .save(event.id, fg_color, bg_color, event.italic);
     }
 
+    /// Handle the "config_changed" event.
+    ///
+    /// Only the tab size is used by the client, in order to place the tab
+    /// stops.
+    fn handle_config_change(&mut self, ctx: &RpcCtx, body: &Value) {
+        #[derive(Deserialize, Debug)]
+        struct Changes {
+            tab_size: Option<usize>,
+        }
+
+        #[derive(Deserialize, Debug)]
+        struct Event {
+            view_id: String,
+            changes: Changes,
+        }
+
+        let event: Event = serde_json::from_value(body.clone()).unwrap();
+
+        if let Some(tab_size) = event.changes.tab_size {
+            self.create_view_if_required(ctx, &event.view_id);
+            self.views
+                .get_mut(&event.view_id)
+                .unwrap()
+                .set_tab_size(tab_size);
+        }
+    }
+
     /// Handle the "scroll_to" event.
     ///
     /// It move the cursor into the given position. If the position is not
@@ -253,6 +738,7 @@ impl EventController {
             annotations: Vec<Annotation>,
             #[serde(rename = "ops")]
             operations: Vec<Operation>,
+            pristine: Option<bool>,
         }
 
         #[derive(Deserialize, Debug)]
@@ -263,12 +749,24 @@ impl EventController {
 
         let event: Event = serde_json::from_value(body.clone()).unwrap();
 
+        if let Some(pristine) = event.update.pristine {
+            let mut state = self.state.lock().unwrap();
+            if pristine {
+                state.unsaved_views.remove(&event.view_id);
+            } else {
+                state.unsaved_views.insert(event.view_id.clone());
+            }
+        }
+
         self.create_view_if_required(ctx, &event.view_id);
         let view = self.views.get_mut(&event.view_id).unwrap();
 
-        view.update_buffer(event.update.operations);
+        view.update_buffer(ctx, event.update.operations, event.update.annotations);
     }
 
+    /// Create the view if it doesn't exist yet.
+    ///
+    /// The new view stays hidden until it is displayed by a pane.
     fn create_view_if_required(&mut self, ctx: &RpcCtx, view_id: &str) {
         if self.views.contains_key(view_id) {
             return;
@@ -276,9 +774,288 @@ impl EventController {
 
         let window = self.layout.create_view_window();
 
-        let new_view = View::new(ctx, &view_id, window, self.styles.clone());
+        let new_view = View::new(
+            ctx,
+            &view_id,
+            window,
+            self.styles.clone(),
+            self.wrap,
+            self.line_numbers,
+            self.sign_column,
+        );
         self.views.insert(view_id.to_string(), new_view);
+    }
+}
+
+/// Replace the first `count` matches of a line with the core "replace_next"
+/// method.
+///
+/// The cursor is put at the start of the line, each replacement moves it
+/// after the replaced match.
+fn replace_in_line(ctx: &RpcCtx, view_id: &str, line: usize, count: usize) {
+    if count == 0 {
+        return;
+    }
+
+    let peer = ctx.get_peer();
+    peer.send_rpc_notification(
+        "edit",
+        &json!({
+            "method": "gesture",
+            "view_id": view_id,
+            "params": { "line": line, "col": 0, "ty": "point_select" }
+        }),
+    );
+
+    for _ in 0..count {
+        peer.send_rpc_notification(
+            "edit",
+            &json!({ "method": "replace_next", "view_id": view_id }),
+        );
+    }
+}
 
-        self.current_view = view_id.to_string();
+/// Answer the "measure_width" request, sent by the core to wrap the lines.
+///
+/// The widths are given in screen columns, whatever the style, with the tab
+/// stops placed every `tab_size` columns.
+fn measure_width(params: &Value, tab_size: usize) -> Result<Value, RemoteError> {
+    #[derive(Deserialize, Debug)]
+    struct Request {
+        strings: Vec<String>,
+    }
+
+    let requests: Vec<Request> = serde_json::from_value(params.clone())
+        .map_err(|err| RemoteError::InvalidRequest(Some(json!(err.to_string()))))?;
+
+    let widths: Vec<Vec<usize>> = requests
+        .iter()
+        .map(|request| {
+            request
+                .strings
+                .iter()
+                .map(|string| width::display_width(string, tab_size))
+                .collect()
+        })
+        .collect();
+
+    Ok(json!(widths))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::measure_width;
+    use super::window::memory;
+    use crate::core::{ClientMessage, Message, Substitution};
+    use crate::split::SplitTree;
+
+    const VIEW_ID: &str = "view-id-1";
+
+    fn core_message(message: Value) -> Message {
+        Message::Core(message.to_string())
+    }
+
+    /// Display the view alone on the screen.
+    fn set_layout() -> Message {
+        Message::Client(ClientMessage::SetLayout {
+            tabs: vec![VIEW_ID.to_owned()],
+            current_tab: 0,
+            panes: SplitTree::new(VIEW_ID),
+            focused_view: VIEW_ID.to_owned(),
+        })
+    }
+
+    /// Replace the content of the view by the given lines and their styles.
+    fn update(lines: &[(&str, Vec<i16>)]) -> Message {
+        let lines: Vec<Value> = lines
+            .iter()
+            .enumerate()
+            .map(|(idx, (text, styles))| {
+                json!({"text": format!("{}\n", text), "ln": idx + 1, "styles": styles})
+            })
+            .collect();
+
+        core_message(json!({
+            "method": "update",
+            "params": {
+                "view_id": VIEW_ID,
+                "update": {
+                    "annotations": [],
+                    "ops": [{"op": "ins", "n": lines.len(), "lines": lines}],
+                    "pristine": true,
+                }
+            }
+        }))
+    }
+
+    fn scroll_to(line: u32, col: u32) -> Message {
+        core_message(json!({
+            "method": "scroll_to",
+            "params": {"view_id": VIEW_ID, "line": line, "col": col}
+        }))
+    }
+
+    /// Replace the matches of "a" by "c".
+    fn substitute(whole_file: bool, global: bool) -> Message {
+        Message::Client(ClientMessage::Substitute {
+            view_id: VIEW_ID.to_owned(),
+            substitution: Substitution {
+                pattern: String::from("a"),
+                replacement: String::from("c"),
+                whole_file,
+                global,
+                case_sensitive: true,
+                regex: false,
+            },
+        })
+    }
+
+    /// Give the matches of a find query, on the given lines starting at 1.
+    fn find_status(chars: Option<&str>, lines: &[usize]) -> Message {
+        core_message(json!({
+            "method": "find_status",
+            "params": {
+                "view_id": VIEW_ID,
+                "queries": [{
+                    "id": 1,
+                    "chars": chars,
+                    "case_sensitive": true,
+                    "is_regex": false,
+                    "whole_words": false,
+                    "matches": lines.len(),
+                    "lines": lines,
+                }]
+            }
+        }))
+    }
+
+    fn replace_status(chars: &str) -> Message {
+        core_message(json!({
+            "method": "replace_status",
+            "params": {
+                "view_id": VIEW_ID,
+                "status": {"chars": chars, "preserve_case": false}
+            }
+        }))
+    }
+
+    #[test]
+    fn test_substitute_first_match_of_each_line() {
+        let messages = vec![
+            set_layout(),
+            update(&[("a a", vec![]), ("b a", vec![]), ("b", vec![])]),
+            substitute(true, false),
+            find_status(Some("a"), &[1, 1, 2]),
+            replace_status("c"),
+            // The core replaces the matches then answers the empty find
+            // query given back to it.
+            update(&[("c a", vec![]), ("b c", vec![]), ("b", vec![])]),
+            find_status(None, &[]),
+        ];
+        let screen = memory::draw(messages, 30, 4).unwrap();
+
+        assert_eq!(" 1 c a\n 2 b c\n 3 b\n2 substitutions", screen.text());
+    }
+
+    #[test]
+    fn test_substitute_all_matches_of_the_cursor_line() {
+        let messages = vec![
+            set_layout(),
+            update(&[("a a", vec![]), ("b a", vec![])]),
+            find_status(Some("b"), &[2]),
+            substitute(false, true),
+            find_status(Some("a"), &[1, 1, 2]),
+            replace_status("c"),
+            update(&[("c c", vec![]), ("b a", vec![])]),
+            find_status(Some("b"), &[2]),
+        ];
+        let screen = memory::draw(messages, 30, 3).unwrap();
+
+        // The search of the user stays in the status bar.
+        assert_eq!(
+            " 1 c c\n 2 b a\n2 substitutions       1 match",
+            screen.text()
+        );
+    }
+
+    #[test]
+    fn test_measure_width_with_the_tab_size() {
+        let params = json!([{"id": 0, "strings": ["\tab", "a\tb"]}]);
+
+        assert_eq!(json!([[10, 9]]), measure_width(&params, 8).unwrap());
+        assert_eq!(json!([[4, 3]]), measure_width(&params, 2).unwrap());
+    }
+
+    #[test]
+    fn test_draw_update() {
+        let messages = vec![
+            set_layout(),
+            update(&[("hello", vec![]), ("world", vec![]), ("!", vec![])]),
+        ];
+        let screen = memory::draw(messages, 10, 5).unwrap();
+
+        assert_eq!(" 1 hello\n 2 world\n 3 !\n\n", screen.text());
+        assert_eq!((0, 3), screen.cursor());
+
+        // The number of the cursor line stands out.
+        let gutter_color = |y| screen.cell(y, 1).unwrap().style.foreground;
+        assert_eq!(Some((255, 200, 0)), gutter_color(0));
+        assert_eq!(Some((255, 0, 0)), gutter_color(1));
+    }
+
+    #[test]
+    fn test_draw_resize() {
+        let messages = vec![
+            set_layout(),
+            update(&[("hello", vec![]), ("world", vec![]), ("!", vec![])]),
+            Message::Client(ClientMessage::Resize {
+                width: 8,
+                height: 3,
+            }),
+        ];
+        let screen = memory::draw(messages, 10, 5).unwrap();
+
+        assert_eq!((8, 3), (screen.width(), screen.height()));
+        assert_eq!(" 1 hello\n 2 world\n", screen.text());
+    }
+
+    #[test]
+    fn test_draw_scroll_to() {
+        let lines: Vec<(&str, Vec<i16>)> = ["a", "b", "c", "d", "e", "fg"]
+            .iter()
+            .map(|text| (*text, vec![]))
+            .collect();
+        let messages = vec![set_layout(), update(&lines), scroll_to(5, 1)];
+        let screen = memory::draw(messages, 10, 5).unwrap();
+
+        assert_eq!(" 3 c\n 4 d\n 5 e\n 6 fg\n", screen.text());
+        assert_eq!((3, 4), screen.cursor());
+    }
+
+    #[test]
+    fn test_draw_def_style() {
+        // The colors are given as little endian RGBA.
+        let color = |r: u32, g: u32, b: u32| r | g << 8 | b << 16 | 255 << 24;
+        let messages = vec![
+            set_layout(),
+            core_message(json!({
+                "method": "def_style",
+                "params": {"id": 2, "fg_color": color(10, 20, 30), "bg_color": color(1, 2, 3)}
+            })),
+            update(&[("let x", vec![0, 3, 2])]),
+        ];
+        let screen = memory::draw(messages, 10, 3).unwrap();
+
+        assert_eq!(" 1 let x\n\n", screen.text());
+
+        let style = |x| screen.cell(0, x).unwrap().style;
+        for x in 3..6 {
+            assert_eq!(Some((10, 20, 30)), style(x).foreground);
+            assert_eq!(Some((1, 2, 3)), style(x).background);
+        }
+        assert_eq!(None, style(6).foreground);
+        assert_eq!(None, style(6).background);
     }
 }
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::style::{
+    StyleID, ADDED_SIGN_STYLE_ID, ERROR_SIGN_STYLE_ID, MODIFIED_SIGN_STYLE_ID,
+    REMOVED_SIGN_STYLE_ID, WARNING_SIGN_STYLE_ID,
+};
+use super::Annotation;
+
+/// The number of columns taken by the sign column: the sign and a space.
+pub const SIGN_COLUMN_WIDTH: usize = 2;
+
+/// The annotation kinds giving a sign to their ranges. Their payloads are
+/// the names of the sign of each range, like `["error", "warning"]`.
+const SIGN_ANNOTATIONS: [&str; 2] = ["diagnostic", "vcs"];
+
+/// The name, the text and the style of each sign, from the most important
+/// to the least important one.
+const SIGNS: [(&str, &str, StyleID); 5] = [
+    ("error", "E", ERROR_SIGN_STYLE_ID),
+    ("warning", "W", WARNING_SIGN_STYLE_ID),
+    ("removed", "-", REMOVED_SIGN_STYLE_ID),
+    ("modified", "~", MODIFIED_SIGN_STYLE_ID),
+    ("added", "+", ADDED_SIGN_STYLE_ID),
+];
+
+/// When the sign column is displayed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SignColumn {
+    /// Only when the view has at least one sign.
+    Auto,
+    Yes,
+    No,
+}
+
+impl SignColumn {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(SignColumn::Auto),
+            "yes" => Some(SignColumn::Yes),
+            "no" => Some(SignColumn::No),
+            _ => None,
+        }
+    }
+}
+
+/// A marker displayed in front of a line. The most important signs are the
+/// smallest ones.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Sign(usize);
+
+impl Sign {
+    pub fn from_name(name: &str) -> Option<Self> {
+        SIGNS
+            .iter()
+            .position(|(sign_name, _, _)| *sign_name == name)
+            .map(Sign)
+    }
+
+    pub fn text(self) -> &'static str {
+        SIGNS[self.0].1
+    }
+
+    pub fn style_id(self) -> StyleID {
+        SIGNS[self.0].2
+    }
+}
+
+/// The sign of each line, only the most important one is kept when a line
+/// is given several signs.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Signs(HashMap<usize, Sign>);
+
+impl Signs {
+    /// Collect the signs given by the plugins through the annotations.
+    ///
+    /// A range spreading over several lines puts its sign on each of them.
+    pub fn from_annotations(annotations: &[Annotation]) -> Self {
+        let mut signs = Signs::default();
+
+        let sign_annotations = annotations
+            .iter()
+            .filter(|annotation| SIGN_ANNOTATIONS.contains(&annotation.kind.as_str()));
+
+        for annotation in sign_annotations {
+            let payloads = match annotation.payloads {
+                Some(Value::Array(ref payloads)) => payloads,
+                _ => {
+                    warn!("{} annotation without payloads", annotation.kind);
+                    continue;
+                }
+            };
+
+            for (range, payload) in annotation.ranges.iter().zip(payloads) {
+                match payload.as_str().and_then(Sign::from_name) {
+                    Some(sign) => (range[0]..=range[2]).for_each(|line| signs.insert(line, sign)),
+                    None => warn!("unknown sign: {}", payload),
+                }
+            }
+        }
+
+        signs
+    }
+
+    pub fn insert(&mut self, line: usize, sign: Sign) {
+        let current = self.0.entry(line).or_insert(sign);
+        *current = (*current).min(sign);
+    }
+
+    pub fn get(&self, line: usize) -> Option<Sign> {
+        self.0.get(&line).cloned()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Annotation;
+    use super::{Sign, Signs};
+
+    fn annotation(kind: &str, ranges: Vec<[usize; 4]>, payloads: &[&str]) -> Annotation {
+        Annotation {
+            kind: kind.to_string(),
+            n: ranges.len(),
+            payloads: Some(json!(payloads)),
+            ranges,
+        }
+    }
+
+    #[test]
+    fn test_signs_keep_the_most_important_one() {
+        let error = Sign::from_name("error").unwrap();
+        let added = Sign::from_name("added").unwrap();
+
+        let mut signs = Signs::default();
+        signs.insert(1, added);
+        signs.insert(1, error);
+        signs.insert(1, added);
+
+        assert_eq!(Some(error), signs.get(1));
+        assert_eq!("E", error.text());
+        assert_eq!(None, signs.get(2));
+    }
+
+    #[test]
+    fn test_signs_from_annotations() {
+        let annotations = vec![
+            annotation(
+                "vcs",
+                vec![[0, 0, 1, 0], [4, 0, 4, 0]],
+                &["added", "removed"],
+            ),
+            annotation("diagnostic", vec![[1, 2, 1, 5]], &["warning"]),
+            annotation("find", vec![[3, 0, 3, 2]], &[]),
+            annotation("vcs", vec![[5, 0, 5, 0]], &["unknown"]),
+        ];
+        let signs = Signs::from_annotations(&annotations);
+
+        assert_eq!(Sign::from_name("added"), signs.get(0));
+        assert_eq!(Sign::from_name("warning"), signs.get(1));
+        assert_eq!(None, signs.get(3));
+        assert_eq!(Sign::from_name("removed"), signs.get(4));
+        assert_eq!(None, signs.get(5));
+    }
+}
@@ -1,19 +1,127 @@
 use super::window::Window;
 
+/// A prompt being edited, like the command line.
+struct Prompt {
+    /// The prompt followed by the content typed so far.
+    text: String,
+    /// The screen column of the cursor.
+    cursor: u32,
+}
+
 pub struct StatusBar {
     window: Box<dyn Window>,
+    mode: String,
+    /// The keys typed so far for an uncompleted key sequence.
+    pending_keys: String,
+    /// The result of the current search.
+    search_status: String,
+    /// A message displayed in place of the mode until the next mode change.
+    message: Option<String>,
+    prompt: Option<Prompt>,
 }
 
 impl StatusBar {
     pub fn new(window: Box<dyn Window>) -> Self {
-        Self { window }
+        Self {
+            window,
+            mode: String::new(),
+            pending_keys: String::new(),
+            search_status: String::new(),
+            message: None,
+            prompt: None,
+        }
+    }
+
+    /// Move the status bar into a new window, after a resize.
+    pub fn set_window(&mut self, window: Box<dyn Window>) {
+        self.window = window;
+        self.redraw();
     }
 
     pub fn update_mode(&mut self, mode: &str) {
+        self.mode = mode.to_owned();
+        self.message = None;
+        self.redraw();
+    }
+
+    pub fn update_pending_keys(&mut self, pending_keys: &str) {
+        self.pending_keys = pending_keys.to_owned();
+        self.redraw();
+    }
+
+    pub fn update_search_status(&mut self, search_status: &str) {
+        self.search_status = search_status.to_owned();
+        self.redraw();
+    }
+
+    pub fn show_message(&mut self, message: &str) {
+        self.message = Some(message.to_owned());
+        self.redraw();
+    }
+
+    /// Display a prompt and move the cursor inside it.
+    ///
+    /// The `cursor` value is the position of the cursor inside `content`, as
+    /// a number of chars.
+    pub fn show_prompt(&mut self, prompt: &str, content: &str, cursor: usize) {
+        let mut text = String::with_capacity(prompt.len() + content.len());
+        text.push_str(prompt);
+        text.push_str(content);
+
+        self.prompt = Some(Prompt {
+            text,
+            cursor: (prompt.chars().count() + cursor) as u32,
+        });
+        self.redraw();
+    }
+
+    pub fn hide_prompt(&mut self) {
+        self.prompt = None;
+        self.redraw();
+    }
+
+    pub fn has_prompt(&self) -> bool {
+        self.prompt.is_some()
+    }
+
+    /// Move the cursor back into the prompt if one is displayed.
+    pub fn focus_prompt(&self) {
+        if let Some(ref prompt) = self.prompt {
+            self.window.move_cursor(0, prompt.cursor);
+            self.window.refresh();
+        }
+    }
+
+    fn redraw(&self) {
+        if let Some(ref prompt) = self.prompt {
+            self.window.move_cursor_and_clear_line(0);
+            self.window.append_str(&prompt.text);
+            self.focus_prompt();
+            return;
+        }
+
         self.window.save_cursor_pos();
         self.window.move_cursor_and_clear_line(0);
 
-        self.window.append_str(&mode);
+        match self.message {
+            Some(ref message) => self.window.append_str(message),
+            None => self.window.append_str(&self.mode),
+        }
+
+        // The search status and the pending keys are aligned on the right.
+        let mut right = self.search_status.clone();
+        if !right.is_empty() && !self.pending_keys.is_empty() {
+            right.push_str("  ");
+        }
+        right.push_str(&self.pending_keys);
+
+        if !right.is_empty() {
+            let width = self.window.get_size().width;
+            let len = right.chars().count() as u32;
+            self.window.move_cursor(0, width.saturating_sub(len + 1));
+            self.window.append_str(&right);
+        }
+
         self.window.restore_cursor_pos();
         self.window.refresh();
     }
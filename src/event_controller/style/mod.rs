@@ -13,6 +13,23 @@ pub const SELECTION_STYLE_ID: StyleID = 0;
 
 pub const LINE_SECTION_STYLE_ID: StyleID = 9999;
 
+/// The style used to highlight the search matches.
+pub const FIND_STYLE_ID: StyleID = 9998;
+
+/// The style of the number of the cursor line inside the gutter.
+pub const CURRENT_LINE_NUMBER_STYLE_ID: StyleID = 9995;
+
+/// The styles of the tab line and of the current tab page label.
+pub const TAB_LINE_STYLE_ID: StyleID = 9997;
+pub const CURRENT_TAB_STYLE_ID: StyleID = 9996;
+
+/// The styles of the signs displayed inside the sign column.
+pub const ERROR_SIGN_STYLE_ID: StyleID = 9994;
+pub const WARNING_SIGN_STYLE_ID: StyleID = 9993;
+pub const ADDED_SIGN_STYLE_ID: StyleID = 9992;
+pub const MODIFIED_SIGN_STYLE_ID: StyleID = 9991;
+pub const REMOVED_SIGN_STYLE_ID: StyleID = 9990;
+
 /// An RGB color description.
 ///
 /// Each value define the amount of a primary color composing it. The possible
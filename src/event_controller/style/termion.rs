@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 
 use super::{
-    RGBColor, Style, StyleID, StyleRange, Styles, LINE_SECTION_STYLE_ID, SELECTION_STYLE_ID,
-    STYLE_LEN,
+    RGBColor, Style, StyleID, StyleRange, Styles, ADDED_SIGN_STYLE_ID,
+    CURRENT_LINE_NUMBER_STYLE_ID, CURRENT_TAB_STYLE_ID, ERROR_SIGN_STYLE_ID, FIND_STYLE_ID,
+    LINE_SECTION_STYLE_ID, MODIFIED_SIGN_STYLE_ID, REMOVED_SIGN_STYLE_ID, SELECTION_STYLE_ID,
+    STYLE_LEN, TAB_LINE_STYLE_ID, WARNING_SIGN_STYLE_ID,
 };
 
 lazy_static! {
@@ -43,6 +45,93 @@ impl TermionStyles {
             false,
         );
 
+        client.save(
+            FIND_STYLE_ID,
+            Some(RGBColor { r: 0, g: 0, b: 0 }),
+            Some(RGBColor {
+                r: 255,
+                g: 215,
+                b: 0,
+            }),
+            false,
+        );
+
+        client.save(
+            CURRENT_LINE_NUMBER_STYLE_ID,
+            Some(RGBColor {
+                r: 255,
+                g: 200,
+                b: 0,
+            }),
+            None,
+            false,
+        );
+
+        client.save(
+            TAB_LINE_STYLE_ID,
+            None,
+            Some(RGBColor {
+                r: 40,
+                g: 40,
+                b: 40,
+            }),
+            false,
+        );
+
+        client.save(
+            CURRENT_TAB_STYLE_ID,
+            Some(RGBColor { r: 0, g: 0, b: 0 }),
+            Some(RGBColor {
+                r: 200,
+                g: 200,
+                b: 200,
+            }),
+            false,
+        );
+
+        client.save(
+            ERROR_SIGN_STYLE_ID,
+            Some(RGBColor { r: 255, g: 0, b: 0 }),
+            None,
+            false,
+        );
+
+        client.save(
+            WARNING_SIGN_STYLE_ID,
+            Some(RGBColor {
+                r: 255,
+                g: 165,
+                b: 0,
+            }),
+            None,
+            false,
+        );
+
+        client.save(
+            ADDED_SIGN_STYLE_ID,
+            Some(RGBColor { r: 0, g: 200, b: 0 }),
+            None,
+            false,
+        );
+
+        client.save(
+            MODIFIED_SIGN_STYLE_ID,
+            Some(RGBColor {
+                r: 0,
+                g: 130,
+                b: 255,
+            }),
+            None,
+            false,
+        );
+
+        client.save(
+            REMOVED_SIGN_STYLE_ID,
+            Some(RGBColor { r: 255, g: 0, b: 0 }),
+            None,
+            false,
+        );
+
         client
     }
 }
@@ -107,16 +196,24 @@ impl Styles for TermionStyles {
             res
         } else {
             let mut res = String::with_capacity(input.len() + STYLE_LEN * styles.len());
+            let mut idx = 0;
             for style in styles {
+                let start = (style.start as usize).min(input.len()).max(idx);
+                let end = (style.end as usize).min(input.len()).max(start);
+
+                // The text between two styles is kept without any style.
+                res.push_str(input.get(idx..start).unwrap_or(""));
                 res.push_str(&format!(
                     "{}{}{}{}{}",
                     style.style.background.unwrap_or_else(|| String::from("")),
                     style.style.foreground.unwrap_or_else(|| String::from("")),
-                    unsafe { input.get_unchecked(style.start as usize..style.end as usize) },
+                    input.get(start..end).unwrap_or(""),
                     BG_RESET.as_str(),
                     FG_RESET.as_str(),
                 ));
+                idx = end;
             }
+            res.push_str(input.get(idx..).unwrap_or(""));
             res
         }
     }
@@ -596,4 +693,19 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn apply_to_keeps_the_text_between_the_styles() {
+        // 0  id1  3        8 id1 11
+        // |-------|        |-------|
+        let styles = vec![0, 3, 1, 5, 3, 1];
+
+        let mut style_registry = TermionStyles::new();
+        style_registry.save(1, Some(RGBColor { r: 255, g: 0, b: 0 }), None, false);
+
+        assert_eq!(
+            "fg(255/0/0)foobg(reset)fg(reset) bar fg(255/0/0)bazbg(reset)fg(reset)!",
+            style_registry.apply_to(styles, "foo bar baz!")
+        );
+    }
 }
@@ -0,0 +1,78 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::style::{Styles, CURRENT_TAB_STYLE_ID, TAB_LINE_STYLE_ID};
+use super::window::Window;
+
+/// The line listing the tab pages, above the views.
+///
+/// It is only displayed when there are several tab pages.
+pub struct TabLine {
+    window: Box<dyn Window>,
+    styles: Rc<RefCell<Box<dyn Styles>>>,
+    /// The label of each tab page.
+    tabs: Vec<String>,
+    current: usize,
+}
+
+impl TabLine {
+    pub fn new(window: Box<dyn Window>, styles: Rc<RefCell<Box<dyn Styles>>>) -> Self {
+        Self {
+            window,
+            styles,
+            tabs: Vec::new(),
+            current: 0,
+        }
+    }
+
+    /// Move the tab line into a new window, after a resize.
+    pub fn set_window(&mut self, window: Box<dyn Window>) {
+        self.window = window;
+        self.redraw();
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.tabs.len() > 1
+    }
+
+    pub fn update(&mut self, tabs: Vec<String>, current: usize) {
+        self.tabs = tabs;
+        self.current = current;
+        self.redraw();
+    }
+
+    fn redraw(&self) {
+        if !self.is_visible() {
+            return;
+        }
+
+        let width = self.window.get_size().width as usize;
+        let styles = self.styles.borrow();
+
+        let mut line = String::new();
+        let mut len = 0;
+        for (idx, label) in self.tabs.iter().enumerate() {
+            // The labels going over the edge of the screen are cut.
+            let label: String = format!(" {} {} ", idx + 1, label)
+                .chars()
+                .take(width - len)
+                .collect();
+            len += label.chars().count();
+
+            let style_id = if idx == self.current {
+                CURRENT_TAB_STYLE_ID
+            } else {
+                TAB_LINE_STYLE_ID
+            };
+            styles.append_with_style(&label, style_id, &mut line);
+        }
+
+        styles.append_with_style(&" ".repeat(width - len), TAB_LINE_STYLE_ID, &mut line);
+
+        self.window.save_cursor_pos();
+        self.window.move_cursor_and_clear_line(0);
+        self.window.append_str(&line);
+        self.window.restore_cursor_pos();
+        self.window.refresh();
+    }
+}
@@ -1,9 +1,14 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use super::style::{Styles, LINE_SECTION_STYLE_ID, STYLE_LEN};
+use super::sign::{SignColumn, Signs, SIGN_COLUMN_WIDTH};
+use super::style::{
+    StyleID, Styles, CURRENT_LINE_NUMBER_STYLE_ID, FIND_STYLE_ID, LINE_SECTION_STYLE_ID,
+    SELECTION_STYLE_ID, STYLE_LEN,
+};
+use super::width::{char_boundary, char_width, display_width, expand_tabs, DEFAULT_TAB_SIZE};
 use super::window::Window;
-use super::Operation;
+use super::{Annotation, Operation};
 
 use xi_rpc::RpcCtx;
 
@@ -11,6 +16,18 @@ pub type ViewID = String;
 
 const SPACES_IN_LINE_SECTION: usize = 2;
 
+/// The text displayed in place of the lines not given by the core yet.
+const MISSING_LINE_PLACEHOLDER: &str = "~";
+
+/// The minimum number of columns kept on the left and on the right of the
+/// cursor when the lines are not wrapped.
+const HORIZONTAL_SCROLL_MARGIN: usize = 5;
+
+/// The rendered annotations with their style, the last ones being drawn on
+/// top of the others.
+const ANNOTATION_STYLES: [(&str, StyleID); 2] =
+    [("find", FIND_STYLE_ID), ("selection", SELECTION_STYLE_ID)];
+
 #[derive(Debug, Clone)]
 pub struct Cursor {
     pub y: u32,
@@ -25,7 +42,8 @@ pub struct Buffer {
 
 #[derive(Debug, Default, Clone)]
 pub struct Line {
-    pub raw: String,
+    pub text: String,
+    pub styles: Vec<StyleID>,
     /// The "real" line number.
     ///
     /// A line wrapped in two lines will keep the same `ln` value.
@@ -35,9 +53,56 @@ pub struct Line {
     pub is_valid: bool,
 }
 
+/// How the lines longer than the window are displayed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wrap {
+    /// Split the long lines into several screen rows instead of cutting them
+    /// at the edge of the window.
+    pub enabled: bool,
+    /// The maximum number of chars of a row. The width of the window is used
+    /// if it is unset or smaller.
+    pub column: Option<usize>,
+}
+
+impl Default for Wrap {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            column: None,
+        }
+    }
+}
+
+/// The numbers displayed inside the gutter.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum LineNumbers {
+    Absolute,
+    /// The distance to the cursor line.
+    Relative,
+    /// The absolute number for the cursor line, the relative ones for the
+    /// others.
+    Hybrid,
+    /// No gutter.
+    Off,
+}
+
+impl LineNumbers {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "absolute" => Some(LineNumbers::Absolute),
+            "relative" => Some(LineNumbers::Relative),
+            "hybrid" => Some(LineNumbers::Hybrid),
+            "off" => Some(LineNumbers::Off),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Eq, PartialEq, Debug)]
 pub enum RedrawBehavior {
     OnlyDirty,
+    /// Only the given lines, by their index inside the buffer.
+    Lines(Vec<usize>),
     Everything,
 }
 
@@ -55,15 +120,44 @@ pub struct View {
     #[allow(dead_code)]
     id: ViewID,
     cursor: Cursor,
+    /// The line of the cursor inside the buffer.
+    cursor_line: usize,
+    /// The column of the cursor inside its line.
+    cursor_col: usize,
     buffer: Buffer,
     window: Box<dyn Window>,
     /// An index pointing to the Line rendered at the top of the screen.
     ///
     /// Changing its value make the screen scoll up/down.
     screen_start: u32,
+    /// The first row of the `screen_start` line rendered at the top of the
+    /// screen, when this line is wrapped on more rows than the window has.
+    screen_start_row: usize,
+    /// The first display column rendered on the left of the screen when the
+    /// lines are not wrapped.
+    screen_start_col: usize,
+    /// The number of columns between two tab stops.
+    tab_size: usize,
     styles: Rc<RefCell<Box<dyn Styles>>>,
+    line_numbers: LineNumbers,
+    /// The width of the whole gutter, made of the line numbers and of the
+    /// sign column.
     width_line_section: u32,
     file_path: Option<String>,
+    /// The annotations (search matches, etc) given with the last update.
+    annotations: Vec<Annotation>,
+    sign_column: SignColumn,
+    /// The signs of the annotations.
+    signs: Signs,
+    /// Only the views displayed inside a pane are drawn on the screen, the
+    /// others keep their state in the background.
+    visible: bool,
+    wrap: Wrap,
+    /// The line and the row inside this line drawn on each screen row by the
+    /// last redraw.
+    drawn_rows: Vec<(usize, usize)>,
+    /// The range of the last "request_lines" sent to the core.
+    requested_lines: Option<(usize, usize)>,
 }
 
 impl View {
@@ -72,25 +166,48 @@ impl View {
         view_id: &str,
         window: Box<dyn Window>,
         styles: Rc<RefCell<Box<dyn Styles>>>,
+        wrap: Wrap,
+        line_numbers: LineNumbers,
+        sign_column: SignColumn,
     ) -> Self {
-        let window_size = window.get_size();
-
         let view = View {
             window,
             styles,
             id: view_id.to_string(),
             cursor: Cursor { y: 0, x: 0 },
+            cursor_line: 0,
+            cursor_col: 0,
             buffer: Buffer::default(),
             screen_start: 0,
+            screen_start_row: 0,
+            screen_start_col: 0,
+            tab_size: DEFAULT_TAB_SIZE,
+            line_numbers,
             width_line_section: 0,
             file_path: None,
+            annotations: Vec::new(),
+            sign_column,
+            signs: Signs::default(),
+            visible: false,
+            wrap,
+            drawn_rows: Vec::new(),
+            requested_lines: None,
         };
 
+        view.send_size(ctx);
+
+        view
+    }
+
+    /// Give the window size and the visible lines to the core.
+    fn send_size(&self, ctx: &RpcCtx) {
+        let window_size = self.window.get_size();
+
         ctx.get_peer().send_rpc_notification(
             "edit",
             &json!({
                 "method": "resize",
-                "view_id": view_id,
+                "view_id": self.id,
                 "params": {
                     "width": window_size.width,
                     "height": window_size.height,
@@ -98,71 +215,355 @@ impl View {
             }),
         );
 
+        self.send_scroll(ctx);
+    }
+
+    /// Give the lines displayed on the screen to the core. The core sends
+    /// the content of the lines it didn't give yet.
+    fn send_scroll(&self, ctx: &RpcCtx) {
+        let window_size = self.window.get_size();
+
         ctx.get_peer().send_rpc_notification(
             "edit",
             &json!({
             "method": "scroll",
-            "view_id": view_id,
-            "params": [0 ,window_size.height + 1] // + 1 bc range not inclusive
+            "view_id": self.id,
+            "params": [self.screen_start, self.screen_start + window_size.height + 1] // + 1 bc range not inclusive
             }),
         );
+    }
 
-        view
+    /// Ask the core for the invalid lines displayed on the screen.
+    ///
+    /// A range is only requested once in order to not flood the core while
+    /// it is loading the file.
+    fn request_missing_lines(&mut self, ctx: &RpcCtx) {
+        let start = self.screen_start as usize;
+        let end = (start + self.window.get_size().height as usize).min(self.buffer.lines.len());
+
+        let mut missing_lines = (start..end).filter(|idx| !self.buffer.lines[*idx].is_valid);
+        let range = match (missing_lines.next(), missing_lines.last()) {
+            (Some(first), Some(last)) => (first, last + 1),
+            (Some(first), None) => (first, first + 1),
+            _ => return,
+        };
+
+        if self.requested_lines == Some(range) {
+            return;
+        }
+
+        self.requested_lines = Some(range);
+        ctx.get_peer().send_rpc_notification(
+            "edit",
+            &json!({
+                "method": "request_lines",
+                "view_id": self.id,
+                "params": [range.0, range.1],
+            }),
+        );
     }
 
     pub fn set_file_path(&mut self, path: &str) {
         self.file_path = Some(path.to_owned());
     }
 
-    pub fn write_to_file(&mut self, ctx: &RpcCtx) {
-        if let Some(ref file_path) = self.file_path {
-            ctx.get_peer().send_rpc_notification(
-                "save",
-                &json!({
-                    "view_id": self.id,
-                    "file_path": file_path,
-                }),
-            );
+    /// Ask the core to save the view into its file.
+    ///
+    /// It returns false if the view has no file path.
+    pub fn write_to_file(&mut self, ctx: &RpcCtx) -> bool {
+        match self.file_path {
+            Some(ref file_path) => {
+                ctx.get_peer().send_rpc_notification(
+                    "save",
+                    &json!({
+                        "view_id": self.id,
+                        "file_path": file_path,
+                    }),
+                );
+                true
+            }
+            None => false,
         }
     }
 
-    pub fn move_cursor(&mut self, _ctx: &RpcCtx, line: u32, col: u32) {
-        let window_size = self.window.get_size();
-        let mut cursor_y = (line as i32) - (self.screen_start as i32);
-
-        let mut scroll: bool = false;
-        if cursor_y >= (window_size.height as i32) {
-            // The cursor is bellow the current screen view. Trigger a scroll.
-            self.screen_start += (cursor_y as u32) - window_size.height + 1;
-            scroll = true;
-            cursor_y -= cursor_y - (window_size.height as i32) + 1
-        } else if cursor_y <= -1 {
-            // The cursor is abor the current screen view. Trigger a scroll.
-            self.screen_start -= cursor_y.checked_abs().unwrap() as u32;
-            scroll = true;
-            cursor_y = 0;
+    /// Display the view inside the given window.
+    pub fn show(&mut self, ctx: &RpcCtx, window: Box<dyn Window>) {
+        self.window = window;
+        self.visible = true;
+
+        // Keep the cursor inside the new window.
+        self.place_cursor();
+
+        self.send_size(ctx);
+        self.request_missing_lines(ctx);
+        self.redraw_view(RedrawBehavior::Everything);
+    }
+
+    pub fn hide(&mut self) {
+        self.visible = false;
+    }
+
+    pub fn tab_size(&self) -> usize {
+        self.tab_size
+    }
+
+    pub fn set_tab_size(&mut self, tab_size: usize) {
+        self.tab_size = tab_size;
+
+        self.place_cursor();
+        self.redraw_view(RedrawBehavior::Everything);
+    }
+
+    pub fn set_line_numbers(&mut self, line_numbers: LineNumbers) {
+        self.line_numbers = line_numbers;
+        self.update_gutter_width();
+
+        self.place_cursor();
+        self.redraw_view(RedrawBehavior::Everything);
+    }
+
+    pub fn set_sign_column(&mut self, sign_column: SignColumn) {
+        self.sign_column = sign_column;
+        self.update_gutter_width();
+
+        self.place_cursor();
+        self.redraw_view(RedrawBehavior::Everything);
+    }
+
+    fn has_sign_column(&self) -> bool {
+        match self.sign_column {
+            SignColumn::Yes => true,
+            SignColumn::Auto => !self.signs.is_empty(),
+            SignColumn::No => false,
         }
+    }
 
-        // Move the cursor at its new position.
-        self.cursor.x = col + self.width_line_section;
-        self.cursor.y = cursor_y as u32;
+    /// The width of the line numbers inside the gutter.
+    fn line_numbers_width(&self) -> usize {
+        match self.line_numbers {
+            LineNumbers::Off => 0,
+            _ => self.buffer.total_len().to_string().len() + SPACES_IN_LINE_SECTION,
+        }
+    }
+
+    /// Compute the width of the gutter from the number of lines and the
+    /// signs.
+    fn update_gutter_width(&mut self) {
+        let sign_column_width = if self.has_sign_column() {
+            SIGN_COLUMN_WIDTH
+        } else {
+            0
+        };
+
+        self.width_line_section = (self.line_numbers_width() + sign_column_width) as u32;
+    }
+
+    pub fn set_wrap(&mut self, wrap: Wrap) {
+        self.wrap = wrap;
+
+        self.place_cursor();
+        self.redraw_view(RedrawBehavior::Everything);
+    }
+
+    /// Put back the screen cursor at the view cursor position.
+    pub fn refresh_cursor(&self) {
+        if !self.visible {
+            return;
+        }
+
+        self.window.move_cursor(self.cursor.y, self.cursor.x);
+        self.window.refresh();
+    }
+
+    pub fn move_cursor(&mut self, ctx: &RpcCtx, line: u32, col: u32) {
+        let previous_line = self.cursor_line;
+        let line_changed = previous_line != line as usize;
+        self.cursor_line = line as usize;
+        self.cursor_col = col as usize;
+
+        let scroll = self.place_cursor();
 
         if scroll {
+            self.send_scroll(ctx);
+            self.request_missing_lines(ctx);
+
             // The scroll require a full redraw
             self.redraw_view(RedrawBehavior::Everything);
+        } else if line_changed && self.line_numbers != LineNumbers::Off {
+            match self.line_numbers {
+                // Only the highlighted number moves to the new cursor line.
+                LineNumbers::Absolute => {
+                    self.redraw_view(RedrawBehavior::Lines(vec![previous_line, self.cursor_line]))
+                }
+                // The gutter numbers depend on the cursor line.
+                _ => self.redraw_view(RedrawBehavior::Everything),
+            }
         } else {
             // No scroll needed so it move the cursor without any redraw.
-            self.window.move_cursor(self.cursor.y, self.cursor.x);
-            self.window.refresh();
+            self.refresh_cursor();
+        }
+    }
+
+    /// Compute the screen position of the cursor from its position inside
+    /// the buffer.
+    ///
+    /// If the cursor is outside the window, the screen scrolls in order to
+    /// display it and it returns true.
+    fn place_cursor(&mut self) -> bool {
+        // The cursor row is kept even inside an empty window.
+        let height = (self.window.get_size().height as usize).max(1);
+        let text_width = self.text_width();
+
+        let previous_start_col = self.screen_start_col;
+        let (cursor_row, cursor_col) = self.cursor_position(text_width);
+        let cursor_x = if self.wrap.enabled {
+            self.screen_start_col = 0;
+            cursor_col
+        } else {
+            self.scroll_to_column(cursor_col, text_width);
+            cursor_col - self.screen_start_col
+        };
+
+        let previous_start = (self.screen_start, self.screen_start_row);
+        if self.cursor_line < self.screen_start as usize {
+            // The cursor is above the screen.
+            self.screen_start = self.cursor_line as u32;
+            self.screen_start_row = 0;
+        } else if self.cursor_line >= self.screen_start as usize + height {
+            // The cursor is far below the screen, each line takes at least
+            // one row.
+            self.screen_start = (self.cursor_line + 1 - height) as u32;
+            self.screen_start_row = 0;
         }
+
+        // The first line can have fewer rows since the last time.
+        let start_rows = self.line_rows(self.screen_start as usize);
+        self.screen_start_row = self.screen_start_row.min(start_rows - 1);
+        if self.cursor_line == self.screen_start as usize {
+            // The cursor is on a row above the screen.
+            self.screen_start_row = self.screen_start_row.min(cursor_row);
+        }
+
+        // Scroll until the row of the cursor is inside the screen.
+        let mut cursor_y = self.rows_between(self.screen_start as usize, self.cursor_line)
+            + cursor_row
+            - self.screen_start_row;
+        while cursor_y >= height {
+            if (self.screen_start as usize) < self.cursor_line {
+                cursor_y -= self.line_rows(self.screen_start as usize) - self.screen_start_row;
+                self.screen_start += 1;
+                self.screen_start_row = 0;
+            } else {
+                // The line of the cursor is taller than the window, the rows
+                // above the cursor one are hidden.
+                self.screen_start_row += cursor_y + 1 - height;
+                cursor_y = height - 1;
+            }
+        }
+
+        self.cursor.x = cursor_x as u32 + self.width_line_section;
+        self.cursor.y = cursor_y as u32;
+
+        (self.screen_start, self.screen_start_row) != previous_start
+            || self.screen_start_col != previous_start_col
+    }
+
+    /// Give the row of the cursor inside its line and its display column
+    /// inside this row.
+    fn cursor_position(&self, text_width: usize) -> (usize, usize) {
+        let text = match self.buffer.lines.get(self.cursor_line) {
+            Some(line) if line.is_valid => line.text.trim_end_matches('\n'),
+            // The line is not known yet, the column is kept as is.
+            _ => return (0, self.cursor_col),
+        };
+
+        let byte_col = char_boundary(text, self.cursor_col);
+        let col = display_width(&text[..byte_col], self.tab_size);
+        if !self.wrap.enabled {
+            return (0, col);
+        }
+
+        let rows = wrap_rows(text, text_width, self.tab_size);
+        let row = rows
+            .iter()
+            .rposition(|row| row.start <= byte_col)
+            .unwrap_or(0);
+
+        // The cursor after a full row goes at the start of the next one.
+        match col - rows[row].col {
+            x if x >= text_width => (row + 1, 0),
+            x => (row, x),
+        }
+    }
+
+    /// Move `screen_start_col` in order to keep the margins around `col`.
+    fn scroll_to_column(&mut self, col: usize, text_width: usize) {
+        let margin = HORIZONTAL_SCROLL_MARGIN.min(text_width.saturating_sub(1) / 2);
+
+        if col < self.screen_start_col + margin {
+            // The cursor is on the left of the screen.
+            self.screen_start_col = col.saturating_sub(margin);
+        } else if col + margin >= self.screen_start_col + text_width {
+            // The cursor is on the right of the screen.
+            self.screen_start_col = col + margin + 1 - text_width;
+        }
+    }
+
+    /// Give the parts of `text` displayed on each screen row.
+    fn rows(&self, text: &str, text_width: usize) -> Vec<Row> {
+        if self.wrap.enabled {
+            wrap_rows(text, text_width, self.tab_size)
+        } else {
+            vec![visible_range(
+                text,
+                self.screen_start_col,
+                text_width,
+                self.tab_size,
+            )]
+        }
+    }
+
+    /// The number of columns of a screen row available for the text.
+    fn text_width(&self) -> usize {
+        let width = (self.window.get_size().width as usize)
+            .saturating_sub(self.width_line_section as usize)
+            .max(1);
+
+        match self.wrap.column {
+            Some(column) if self.wrap.enabled && column > 0 => width.min(column),
+            _ => width,
+        }
+    }
+
+    /// The number of screen rows taken by the line at `line_idx`.
+    fn line_rows(&self, line_idx: usize) -> usize {
+        match self.buffer.lines.get(line_idx) {
+            Some(line) => self.rows(&line.text, self.text_width()).len(),
+            None => 1,
+        }
+    }
+
+    /// The number of screen rows taken by the lines from `start` to `end`
+    /// (excluded).
+    fn rows_between(&self, start: usize, end: usize) -> usize {
+        (start..end).map(|line_idx| self.line_rows(line_idx)).sum()
     }
 
-    pub fn update_buffer(&mut self, operations: Vec<Operation>) {
+    /// Give the line of the cursor inside the buffer.
+    pub fn cursor_line(&self) -> usize {
+        self.cursor_line
+    }
+
+    pub fn update_buffer(
+        &mut self,
+        ctx: &RpcCtx,
+        operations: Vec<Operation>,
+        annotations: Vec<Annotation>,
+    ) {
         let mut new_buffer = Buffer::default();
         let mut old_idx: usize = 0;
         let mut new_idx: usize = 0;
 
-        let styles = self.styles.borrow();
         for operation in operations {
             match operation.kind.as_str() {
                 "copy" => {
@@ -171,10 +572,11 @@ impl View {
                     for i in 0..operation.n {
                         let old_buffer = &self.buffer.lines[old_idx + i];
                         new_buffer.lines.push(Line {
-                            raw: old_buffer.raw.clone(),
+                            text: old_buffer.text.clone(),
+                            styles: old_buffer.styles.clone(),
                             ln: operation.ln.map(|ln| ln + i),
                             is_dirty,
-                            is_valid: true,
+                            is_valid: old_buffer.is_valid,
                         });
                         new_idx += 1;
                     }
@@ -185,7 +587,8 @@ impl View {
                 "invalidate" => {
                     for _ in 0..operation.n {
                         new_buffer.lines.push(Line {
-                            raw: String::from(""),
+                            text: String::new(),
+                            styles: Vec::new(),
                             ln: None,
                             is_dirty: true,
                             is_valid: false,
@@ -195,7 +598,8 @@ impl View {
                 "ins" => {
                     for line in operation.lines.unwrap() {
                         new_buffer.lines.push(Line {
-                            raw: styles.apply_to(line.styles, &line.text),
+                            text: line.text,
+                            styles: line.styles,
                             ln: line.ln,
                             is_dirty: true,
                             is_valid: true,
@@ -207,67 +611,451 @@ impl View {
             }
         }
 
-        self.width_line_section =
-            ((new_buffer.total_len().to_string().len()) + SPACES_IN_LINE_SECTION) as u32;
-
         self.buffer = new_buffer;
-        self.redraw_view(RedrawBehavior::OnlyDirty);
+
+        // Once the requested lines are given, the same range can be requested
+        // again if the core invalidates it later.
+        if let Some((start, end)) = self.requested_lines {
+            let mut requested = self.buffer.lines.iter().skip(start).take(end - start);
+            if requested.all(|line| line.is_valid) {
+                self.requested_lines = None;
+            }
+        }
+
+        // The annotations can move on any line, even the ones without any
+        // content change.
+        let annotations_changed = annotations != self.annotations;
+        if annotations_changed {
+            self.signs = Signs::from_annotations(&annotations);
+            self.annotations = annotations;
+        }
+
+        let previous_gutter_width = self.width_line_section;
+        self.update_gutter_width();
+
+        // The wrapped lines above the cursor can change their number of rows.
+        let scroll = self.place_cursor();
+        if scroll {
+            self.send_scroll(ctx);
+        }
+        self.request_missing_lines(ctx);
+
+        if scroll || annotations_changed || previous_gutter_width != self.width_line_section {
+            self.redraw_view(RedrawBehavior::Everything);
+        } else {
+            self.redraw_view(RedrawBehavior::OnlyDirty);
+        }
     }
 
-    pub fn redraw_view(&self, redraw_behavior: RedrawBehavior) {
+    pub fn redraw_view(&mut self, redraw_behavior: RedrawBehavior) {
+        if !self.visible {
+            return;
+        }
+
         let window_size = self.window.get_size();
+        let text_width = self.text_width();
         let styles_registry = self.styles.borrow();
 
-        let buffer_len =
-            if self.buffer.lines_availables_after(self.screen_start) < window_size.height {
-                // The number of lines inside the buffer is less than the available lines on the screen so
-                // it print all the remaining of the buffer.
-                self.buffer.lines_availables_after(self.screen_start)
-            } else {
-                // The number of lines inside the buffer is greater than the available lines on the screen so
-                // it print only what the screen is able to show.
-                window_size.height
-            };
-
+        let mut drawn_rows = Vec::with_capacity(window_size.height as usize);
         let buffer_iter = self
             .buffer
             .lines
             .iter()
-            .skip(self.screen_start as usize)
-            .take(buffer_len as usize);
+            .enumerate()
+            .skip(self.screen_start as usize);
+
+        'lines: for (line_idx, line) in buffer_iter {
+            let rows = self.rows(&line.text, text_width);
+            let styles = line_styles(line, line_idx, &self.annotations);
 
-        for (screen_line, line) in buffer_iter.enumerate() {
-            if redraw_behavior == RedrawBehavior::Everything || line.is_dirty {
-                self.window.move_cursor_and_clear_line(screen_line as u32);
+            // The top line can start with some hidden rows.
+            let hidden_rows = if line_idx == self.screen_start as usize {
+                self.screen_start_row
+            } else {
+                0
+            };
+
+            for (row_idx, row) in rows.iter().enumerate().skip(hidden_rows) {
+                let screen_row = drawn_rows.len();
+                if screen_row >= window_size.height as usize {
+                    break 'lines;
+                }
+                drawn_rows.push((line_idx, row_idx));
 
-                // Print the line number.
-                let ln = match line.ln {
-                    Some(ln) => ln.to_string(),
-                    None => String::from(""),
+                // A row is drawn again if its line changed or if an another
+                // line was drawn at its place.
+                let has_moved = self.drawn_rows.get(screen_row) != Some(&(line_idx, row_idx));
+                let is_skipped = match redraw_behavior {
+                    RedrawBehavior::OnlyDirty => !line.is_dirty && !has_moved,
+                    RedrawBehavior::Lines(ref lines) => !lines.contains(&line_idx) && !has_moved,
+                    RedrawBehavior::Everything => false,
                 };
+                if is_skipped {
+                    continue;
+                }
 
-                let line_size = (self.width_line_section as usize) - SPACES_IN_LINE_SECTION;
-                let mut line_section = String::with_capacity(line_size + STYLE_LEN);
-                styles_registry.append_with_style(
-                    &format!(" {:width$} ", ln, width = line_size),
-                    LINE_SECTION_STYLE_ID,
-                    &mut line_section,
-                );
+                self.window.move_cursor_and_clear_line(screen_row as u32);
+
+                // Print the line number, only on the first row of the line.
+                if self.line_numbers != LineNumbers::Off {
+                    let ln = match row_idx {
+                        0 => gutter_number(self.line_numbers, line, line_idx, self.cursor_line),
+                        _ => String::new(),
+                    };
+                    let style_id = if line_idx == self.cursor_line {
+                        CURRENT_LINE_NUMBER_STYLE_ID
+                    } else {
+                        LINE_SECTION_STYLE_ID
+                    };
+
+                    let line_size = self.line_numbers_width() - SPACES_IN_LINE_SECTION;
+                    let mut line_section = String::with_capacity(line_size + STYLE_LEN);
+                    styles_registry.append_with_style(
+                        &format!(" {:>width$} ", ln, width = line_size),
+                        style_id,
+                        &mut line_section,
+                    );
 
-                self.window.append_str(&line_section);
-                self.window.append_str(&line.raw);
+                    self.window.append_str(&line_section);
+                }
+
+                // Print the sign, only on the first row of the line.
+                if self.has_sign_column() {
+                    let mut sign_section = String::with_capacity(SIGN_COLUMN_WIDTH + STYLE_LEN);
+                    match self.signs.get(line_idx).filter(|_| row_idx == 0) {
+                        Some(sign) => styles_registry.append_with_style(
+                            &format!("{} ", sign.text()),
+                            sign.style_id(),
+                            &mut sign_section,
+                        ),
+                        None => sign_section.push_str(&" ".repeat(SIGN_COLUMN_WIDTH)),
+                    }
+
+                    self.window.append_str(&sign_section);
+                }
+
+                if !line.is_valid {
+                    let mut placeholder = String::with_capacity(STYLE_LEN + 1);
+                    styles_registry.append_with_style(
+                        MISSING_LINE_PLACEHOLDER,
+                        LINE_SECTION_STYLE_ID,
+                        &mut placeholder,
+                    );
+                    self.window.append_str(&placeholder);
+                    continue;
+                }
+
+                // Without wrap, a wide char cut by the left edge of the screen
+                // leaves an empty column.
+                if !self.wrap.enabled {
+                    let padding = row.col.saturating_sub(self.screen_start_col);
+                    self.window.append_str(&" ".repeat(padding));
+                }
+
+                let (text, row_styles) = expand_tabs(
+                    &line.text[row.start..row.end],
+                    slice_styles(&styles, row.start, row.end),
+                    row.col,
+                    self.tab_size,
+                );
+                let raw = styles_registry.apply_to(row_styles, &text);
+                self.window.append_str(&raw);
             }
         }
 
         // If the buffer to draw do not fill the screen, iter on the remaining
         // lines of the screen and clear it.
-        if buffer_len < window_size.height {
-            for screen_line in buffer_len..window_size.height {
-                self.window.move_cursor_and_clear_line(screen_line as u32);
-            }
+        for screen_line in drawn_rows.len() as u32..window_size.height {
+            self.window.move_cursor_and_clear_line(screen_line);
         }
 
+        drop(styles_registry);
+        self.drawn_rows = drawn_rows;
+
         self.window.move_cursor(self.cursor.y, self.cursor.x);
         self.window.refresh();
     }
 }
+
+/// Give the number displayed inside the gutter for the line at `line_idx`.
+///
+/// The lines not given by the core yet have no number.
+fn gutter_number(
+    line_numbers: LineNumbers,
+    line: &Line,
+    line_idx: usize,
+    cursor_line: usize,
+) -> String {
+    let ln = match line.ln {
+        Some(ln) => ln,
+        None => return String::new(),
+    };
+
+    let distance = if line_idx > cursor_line {
+        line_idx - cursor_line
+    } else {
+        cursor_line - line_idx
+    };
+
+    match line_numbers {
+        LineNumbers::Absolute => ln.to_string(),
+        LineNumbers::Hybrid if distance == 0 => ln.to_string(),
+        LineNumbers::Relative | LineNumbers::Hybrid => distance.to_string(),
+        LineNumbers::Off => String::new(),
+    }
+}
+
+/// A part of a line displayed on a screen row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Row {
+    /// The byte range of the row inside its line.
+    start: usize,
+    end: usize,
+    /// The display column of `start` inside the line.
+    col: usize,
+}
+
+/// Split `text` into rows of at most `width` display columns.
+fn wrap_rows(text: &str, width: usize, tab_size: usize) -> Vec<Row> {
+    let text = text.trim_end_matches('\n');
+
+    let mut rows = Vec::new();
+    let mut row = Row {
+        start: 0,
+        end: 0,
+        col: 0,
+    };
+    let mut col = 0;
+    for (idx, c) in text.char_indices() {
+        let len = char_width(c, col, tab_size);
+
+        // A char which doesn't fit goes on the next row, a row has at least
+        // one char.
+        if col + len > row.col + width && idx > row.start {
+            row.end = idx;
+            rows.push(row);
+            row = Row {
+                start: idx,
+                end: idx,
+                col,
+            };
+        }
+
+        col += len;
+    }
+
+    row.end = text.len();
+    rows.push(row);
+    rows
+}
+
+/// Give the part of `text` displayed on the `width` display columns starting
+/// at `start_col`.
+///
+/// A wide char cut by one of the edges is not displayed.
+fn visible_range(text: &str, start_col: usize, width: usize, tab_size: usize) -> Row {
+    let text = text.trim_end_matches('\n');
+
+    let mut row = Row {
+        start: text.len(),
+        end: text.len(),
+        col: start_col,
+    };
+    let mut is_started = false;
+    let mut col = 0;
+    for (idx, c) in text.char_indices() {
+        let len = char_width(c, col, tab_size);
+
+        if !is_started && col >= start_col {
+            is_started = true;
+            row.start = idx;
+            row.col = col;
+        }
+
+        if is_started && col + len > start_col + width {
+            row.end = idx;
+            break;
+        }
+
+        col += len;
+    }
+
+    row
+}
+
+/// Keep the part of the xi style triplets between the `start` and `end`
+/// bytes, with the offsets relative to `start`.
+fn slice_styles(styles: &[StyleID], start: usize, end: usize) -> Vec<StyleID> {
+    let mut sliced = Vec::with_capacity(styles.len());
+
+    let mut span_end: i32 = 0;
+    let mut sliced_end: i32 = 0;
+    for triplet in styles.chunks(3) {
+        let span_start = span_end + i32::from(triplet[0]);
+        span_end = span_start + i32::from(triplet[1]);
+
+        let clipped_start = span_start.max(start as i32);
+        let clipped_end = span_end.min(end as i32);
+        if clipped_start >= clipped_end {
+            continue;
+        }
+
+        let relative_start = clipped_start - start as i32;
+        sliced.push((relative_start - sliced_end) as StyleID);
+        sliced.push((clipped_end - clipped_start) as StyleID);
+        sliced.push(triplet[2]);
+        sliced_end = clipped_end - start as i32;
+    }
+
+    sliced
+}
+
+/// Give the styles of the line at `line_idx` with the annotations appended
+/// as xi style triplets, on top of the syntax styles.
+fn line_styles(line: &Line, line_idx: usize, annotations: &[Annotation]) -> Vec<StyleID> {
+    let mut styles = line.styles.clone();
+
+    // The triplet starts are relative to the end of the previous one.
+    let mut end: i32 = 0;
+    for triplet in styles.chunks(3) {
+        end += i32::from(triplet[0]) + i32::from(triplet[1]);
+    }
+
+    for (kind, style_id) in ANNOTATION_STYLES.iter() {
+        let ranges = annotations
+            .iter()
+            .filter(|annotation| annotation.kind == *kind)
+            .flat_map(|annotation| annotation.ranges.iter());
+
+        for range in ranges {
+            let [start_line, start_col, end_line, end_col] = *range;
+            if line_idx < start_line || line_idx > end_line {
+                continue;
+            }
+
+            let start = if line_idx == start_line { start_col } else { 0 };
+            let stop = if line_idx == end_line {
+                end_col.min(line.text.len())
+            } else {
+                line.text.len()
+            };
+
+            // The carets are drawn by the terminal cursor.
+            if start >= stop {
+                continue;
+            }
+
+            styles.push((start as i32 - end) as StyleID);
+            styles.push((stop - start) as StyleID);
+            styles.push(*style_id);
+            end = stop as i32;
+        }
+    }
+
+    styles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn annotation(kind: &str, ranges: Vec<[usize; 4]>) -> Annotation {
+        Annotation {
+            kind: kind.to_owned(),
+            n: ranges.len(),
+            payloads: None,
+            ranges,
+        }
+    }
+
+    #[test]
+    fn test_line_styles_with_annotations() {
+        let line = Line {
+            text: String::from("foo bar baz\n"),
+            styles: vec![0, 3, 2],
+            ln: Some(2),
+            is_dirty: true,
+            is_valid: true,
+        };
+
+        let annotations = vec![
+            annotation("selection", vec![[0, 2, 1, 5], [1, 9, 1, 9]]),
+            annotation("find", vec![[1, 8, 1, 11], [2, 0, 2, 3]]),
+        ];
+
+        // The selection is drawn after the find highlight and the caret is
+        // ignored.
+        assert_eq!(
+            vec![0, 3, 2, 5, 3, FIND_STYLE_ID, -11, 5, SELECTION_STYLE_ID],
+            line_styles(&line, 1, &annotations)
+        );
+
+        // The ranges outside of the line are ignored.
+        assert_eq!(vec![0, 3, 2], line_styles(&line, 3, &annotations));
+    }
+
+    fn row(start: usize, end: usize, col: usize) -> Row {
+        Row { start, end, col }
+    }
+
+    #[test]
+    fn test_wrap_rows() {
+        assert_eq!(
+            vec![row(0, 4, 0), row(4, 8, 4), row(8, 10, 8)],
+            wrap_rows("abcdefghij\n", 4, 4)
+        );
+        assert_eq!(
+            vec![row(0, 4, 0), row(4, 8, 4)],
+            wrap_rows("abcdefgh", 4, 4)
+        );
+        assert_eq!(vec![row(0, 0, 0)], wrap_rows("\n", 4, 4));
+
+        // The rows are cut on the display columns, not on the bytes.
+        assert_eq!(vec![row(0, 5, 0), row(5, 6, 3)], wrap_rows("éaéb", 3, 4));
+        assert_eq!(vec![row(0, 6, 0), row(6, 9, 4)], wrap_rows("日本語", 5, 4));
+
+        // The tab goes up to the end of the first row.
+        assert_eq!(vec![row(0, 2, 0), row(2, 3, 4)], wrap_rows("a\tb", 4, 4));
+    }
+
+    #[test]
+    fn test_visible_range() {
+        assert_eq!(row(0, 4, 0), visible_range("abcdefghij\n", 0, 4, 4));
+        assert_eq!(row(3, 7, 3), visible_range("abcdefghij\n", 3, 4, 4));
+        assert_eq!(row(8, 10, 8), visible_range("abcdefghij\n", 8, 4, 4));
+        assert_eq!(row(10, 10, 12), visible_range("abcdefghij\n", 12, 4, 4));
+        assert_eq!(row(2, 6, 1), visible_range("éaéb", 1, 4, 4));
+
+        // The "日" cut by the left edge is not displayed.
+        assert_eq!(row(3, 6, 2), visible_range("日本語", 1, 4, 4));
+    }
+
+    #[test]
+    fn test_gutter_number() {
+        let line = Line {
+            text: String::from("foo\n"),
+            styles: Vec::new(),
+            ln: Some(8),
+            is_dirty: true,
+            is_valid: true,
+        };
+
+        assert_eq!("8", gutter_number(LineNumbers::Absolute, &line, 7, 4));
+        assert_eq!("3", gutter_number(LineNumbers::Relative, &line, 7, 4));
+        assert_eq!("3", gutter_number(LineNumbers::Hybrid, &line, 7, 10));
+        assert_eq!("0", gutter_number(LineNumbers::Relative, &line, 7, 7));
+        assert_eq!("8", gutter_number(LineNumbers::Hybrid, &line, 7, 7));
+        assert_eq!("", gutter_number(LineNumbers::Off, &line, 7, 7));
+    }
+
+    #[test]
+    fn test_slice_styles() {
+        // 0  id2  3        8 id3 11
+        // |-------|        |-------|
+        let styles = vec![0, 3, 2, 5, 3, 3];
+
+        assert_eq!(vec![0, 1, 2, 5, 2, 3], slice_styles(&styles, 2, 10));
+        assert_eq!(vec![0, 1, 3], slice_styles(&styles, 10, 12));
+        assert!(slice_styles(&styles, 4, 7).is_empty());
+    }
+}
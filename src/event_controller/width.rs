@@ -0,0 +1,130 @@
+use super::style::StyleID;
+
+use unicode_width::UnicodeWidthChar;
+
+/// The tab size used until the core gives the one of the view.
+pub const DEFAULT_TAB_SIZE: usize = 4;
+
+/// Give the number of screen columns taken by `c` when it is displayed at the
+/// column `col`.
+///
+/// A tab goes to the next tab stop. The control chars and the combining marks
+/// don't take any column and the East Asian wide chars take two columns.
+pub fn char_width(c: char, col: usize, tab_size: usize) -> usize {
+    if c == '\t' {
+        let tab_size = tab_size.max(1);
+        tab_size - col % tab_size
+    } else {
+        c.width().unwrap_or(0)
+    }
+}
+
+/// Give the number of screen columns taken by `text` displayed at the start
+/// of a line.
+pub fn display_width(text: &str, tab_size: usize) -> usize {
+    text.chars()
+        .fold(0, |col, c| col + char_width(c, col, tab_size))
+}
+
+/// Give the biggest char boundary of `text` lower or equal to `idx`.
+pub fn char_boundary(text: &str, idx: usize) -> usize {
+    (0..=idx.min(text.len()))
+        .rev()
+        .find(|idx| text.is_char_boundary(*idx))
+        .unwrap_or(0)
+}
+
+/// Replace the tabs of `text` by spaces up to their tab stop, `col` being the
+/// display column of the start of `text` inside its line.
+///
+/// The xi style triplets are moved along with the text.
+pub fn expand_tabs(
+    text: &str,
+    styles: Vec<StyleID>,
+    col: usize,
+    tab_size: usize,
+) -> (String, Vec<StyleID>) {
+    if !text.contains('\t') {
+        return (text.to_owned(), styles);
+    }
+
+    // The offset inside the expanded text of each byte of `text`.
+    let mut offsets = Vec::with_capacity(text.len() + 1);
+    let mut expanded = String::with_capacity(text.len());
+    let mut col = col;
+    for c in text.chars() {
+        offsets.extend((0..c.len_utf8()).map(|_| expanded.len()));
+
+        let width = char_width(c, col, tab_size);
+        if c == '\t' {
+            expanded.extend((0..width).map(|_| ' '));
+        } else {
+            expanded.push(c);
+        }
+        col += width;
+    }
+    offsets.push(expanded.len());
+
+    let offset = |idx: i32| offsets[(idx.max(0) as usize).min(text.len())] as i32;
+
+    let mut moved = Vec::with_capacity(styles.len());
+    let mut span_end: i32 = 0;
+    let mut moved_end: i32 = 0;
+    for triplet in styles.chunks(3) {
+        let span_start = span_end + i32::from(triplet[0]);
+        span_end = span_start + i32::from(triplet[1]);
+
+        let (start, end) = (offset(span_start), offset(span_end));
+        moved.push((start - moved_end) as StyleID);
+        moved.push((end - start) as StyleID);
+        moved.push(triplet[2]);
+        moved_end = end;
+    }
+
+    (expanded, moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_char_width() {
+        assert_eq!(1, char_width('a', 0, 4));
+        assert_eq!(2, char_width('日', 0, 4));
+        assert_eq!(0, char_width('\u{301}', 0, 4));
+        assert_eq!(4, char_width('\t', 0, 4));
+        assert_eq!(1, char_width('\t', 3, 4));
+        assert_eq!(2, char_width('\t', 6, 8));
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(5, display_width("a\tb", 4));
+        assert_eq!(4, display_width("日本", 4));
+        assert_eq!(1, display_width("e\u{301}", 4));
+        assert_eq!(2, display_width("🦀", 4));
+    }
+
+    #[test]
+    fn test_char_boundary() {
+        assert_eq!(0, char_boundary("éa", 1));
+        assert_eq!(2, char_boundary("éa", 2));
+        assert_eq!(3, char_boundary("éa", 10));
+    }
+
+    #[test]
+    fn test_expand_tabs() {
+        // The tab of "a\tbc" goes up to the column 4 and the style of "bc"
+        // moves along.
+        assert_eq!(
+            (String::from("a   bc"), vec![0, 1, 2, 3, 2, 3]),
+            expand_tabs("a\tbc", vec![0, 1, 2, 1, 2, 3], 0, 4)
+        );
+
+        // The tab stops depend on the column of the text inside its line.
+        let (text, styles) = expand_tabs("\tb", Vec::new(), 2, 4);
+        assert_eq!("  b", text);
+        assert!(styles.is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::screen::Screen;
+use super::window::MemoryWindow;
+use crate::event_controller::view::ViewID;
+use crate::event_controller::window::{Layout, Window, WindowPosition, WindowSize};
+use crate::split::{SplitDirection, SplitTree};
+
+const STATUS_HEIGHT: u32 = 1;
+const TAB_LINE_HEIGHT: u32 = 1;
+
+/// A layout drawing into a screen kept in memory instead of the terminal.
+///
+/// It is used to replay the recorded sessions and to test the rendering
+/// without a terminal.
+pub struct MemoryLayout {
+    screen: Rc<RefCell<Screen>>,
+    tab_line_visible: bool,
+}
+
+impl MemoryLayout {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            screen: Rc::new(RefCell::new(Screen::new(width, height))),
+            tab_line_visible: false,
+        }
+    }
+
+    /// Give the screen drawn by the windows of the layout.
+    pub fn screen(&self) -> Rc<RefCell<Screen>> {
+        self.screen.clone()
+    }
+
+    fn size(&self) -> (u32, u32) {
+        let screen = self.screen.borrow();
+        (screen.width(), screen.height())
+    }
+
+    /// Give the position and the size of the space available for the views,
+    /// between the tab line and the status bar.
+    fn view_area(&self) -> (WindowPosition, WindowSize) {
+        let (width, height) = self.size();
+        let top = if self.tab_line_visible {
+            TAB_LINE_HEIGHT
+        } else {
+            0
+        };
+
+        (
+            WindowPosition { y: top, x: 0 },
+            WindowSize {
+                // At least one row is left to the views on tiny screens.
+                height: height.saturating_sub(STATUS_HEIGHT + top).max(1),
+                width,
+            },
+        )
+    }
+
+    fn create_window(&self, pos: WindowPosition, size: WindowSize) -> Box<dyn Window> {
+        Box::new(MemoryWindow::new(self.screen.clone(), pos, size))
+    }
+}
+
+impl Layout for MemoryLayout {
+    fn create_view_window(&self) -> Box<dyn Window> {
+        let (pos, size) = self.view_area();
+        self.create_window(pos, size)
+    }
+
+    fn create_view_windows(&self, tree: &SplitTree) -> Vec<(ViewID, Box<dyn Window>)> {
+        let (pos, size) = self.view_area();
+        let (panes, separators) = tree.geometry(pos, size);
+
+        {
+            let mut screen = self.screen.borrow_mut();
+            for separator in separators {
+                let (x, y) = (separator.pos.x, separator.pos.y);
+                match separator.direction {
+                    SplitDirection::Horizontal => {
+                        screen.move_cursor(y, x);
+                        screen.put_str(&"─".repeat(separator.size.width as usize));
+                    }
+                    SplitDirection::Vertical => {
+                        for line in 0..separator.size.height {
+                            screen.move_cursor(y + line, x);
+                            screen.put_str("│");
+                        }
+                    }
+                }
+            }
+        }
+
+        panes
+            .into_iter()
+            .map(|pane| (pane.view_id, self.create_window(pane.pos, pane.size)))
+            .collect()
+    }
+
+    fn create_new_status_bar_window(&self) -> Box<dyn Window> {
+        let (width, height) = self.size();
+
+        self.create_window(
+            WindowPosition {
+                y: height.saturating_sub(STATUS_HEIGHT),
+                x: 0,
+            },
+            WindowSize {
+                height: STATUS_HEIGHT,
+                width,
+            },
+        )
+    }
+
+    fn create_tab_line_window(&self) -> Box<dyn Window> {
+        let (width, _) = self.size();
+
+        self.create_window(
+            WindowPosition { y: 0, x: 0 },
+            WindowSize {
+                height: TAB_LINE_HEIGHT,
+                width,
+            },
+        )
+    }
+
+    fn set_tab_line_visible(&mut self, visible: bool) {
+        self.tab_line_visible = visible;
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        *self.screen.borrow_mut() = Screen::new(width, height);
+    }
+}
@@ -0,0 +1,52 @@
+//! A backend drawing into a screen kept in memory instead of the terminal,
+//! for the replays and the rendering tests.
+
+mod layout;
+mod screen;
+mod window;
+
+pub use self::layout::MemoryLayout;
+pub use self::screen::Screen;
+
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+
+use crate::core::{reader_of, Message};
+use crate::event_controller::style::TermionStyles;
+use crate::event_controller::{EventController, Styles};
+use crate::state::SharedState;
+
+use failure::Error;
+use xi_rpc::{ReadError, RpcLoop};
+
+/// Feed the messages to a new EventController drawing into a memory screen
+/// of the given size, and give this screen once they are all handled.
+///
+/// The messages sent back to the core are dropped.
+pub fn draw(messages: Vec<Message>, width: u32, height: u32) -> Result<Screen, Error> {
+    let mut reader = reader_of(messages);
+    let client_messages = reader.client_messages();
+
+    let layout = MemoryLayout::new(width, height);
+    let screen = layout.screen();
+
+    let styles: Rc<RefCell<Box<dyn Styles>>> =
+        Rc::new(RefCell::new(Box::new(TermionStyles::new())));
+    let mut event_handler = EventController::new(
+        Box::new(layout),
+        styles,
+        SharedState::default(),
+        client_messages,
+    );
+
+    // The end of the messages disconnects the loop.
+    let mut event_loop = RpcLoop::new(io::sink());
+    match event_loop.mainloop(|| reader, &mut event_handler) {
+        Ok(()) | Err(ReadError::Disconnect) => (),
+        Err(err) => return Err(format_err!("the event controller stopped: {:?}", err)),
+    }
+
+    let screen = screen.borrow().clone();
+    Ok(screen)
+}
@@ -0,0 +1,233 @@
+use crate::event_controller::width::char_width;
+
+/// The cell following a wide char, covered by it.
+const WIDE_CHAR_TAIL: char = '\0';
+
+/// The style of a cell, set by the SGR escape sequences written before its
+/// char.
+///
+/// The styles are only read by the tests, the replay gives the text.
+#[cfg_attr(not(test), allow(dead_code))]
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct CellStyle {
+    /// The RGB colors, `None` for the default color of the terminal.
+    pub foreground: Option<(u8, u8, u8)>,
+    pub background: Option<(u8, u8, u8)>,
+    pub italic: bool,
+}
+
+#[cfg_attr(not(test), allow(dead_code))]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Cell {
+    pub c: char,
+    pub style: CellStyle,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            c: ' ',
+            style: CellStyle::default(),
+        }
+    }
+}
+
+/// A grid of cells standing for the terminal, written by the memory
+/// windows.
+#[derive(Debug, Clone)]
+pub struct Screen {
+    width: u32,
+    height: u32,
+    cells: Vec<Vec<Cell>>,
+    /// The cursor position as `(y, x)`.
+    cursor: (u32, u32),
+    saved_cursor: (u32, u32),
+    /// The style given to the next written chars.
+    pen: CellStyle,
+}
+
+impl Screen {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![vec![Cell::default(); width as usize]; height as usize],
+            cursor: (0, 0),
+            saved_cursor: (0, 0),
+            pen: CellStyle::default(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Give the cursor position as `(y, x)`.
+    #[cfg(test)]
+    pub fn cursor(&self) -> (u32, u32) {
+        self.cursor
+    }
+
+    /// Give the cell at the given position, `None` outside of the screen.
+    #[cfg(test)]
+    pub fn cell(&self, y: u32, x: u32) -> Option<&Cell> {
+        self.cells
+            .get(y as usize)
+            .and_then(|row| row.get(x as usize))
+    }
+
+    pub fn move_cursor(&mut self, y: u32, x: u32) {
+        self.cursor = (y, x);
+    }
+
+    pub fn save_cursor(&mut self) {
+        self.saved_cursor = self.cursor;
+    }
+
+    pub fn restore_cursor(&mut self) {
+        self.cursor = self.saved_cursor;
+    }
+
+    /// Write `s` at the cursor position and move the cursor after it.
+    ///
+    /// The SGR escape sequences change the style of the following chars, the
+    /// other ones are skipped. The text going out of the screen is dropped.
+    pub fn put_str(&mut self, s: &str) {
+        let mut chars = s.chars();
+
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                self.read_escape_sequence(&mut chars);
+                continue;
+            }
+
+            let width = char_width(c, 0, 1) as u32;
+            if width == 0 {
+                continue;
+            }
+
+            let (y, x) = self.cursor;
+            if y < self.height && x + width <= self.width {
+                let style = self.pen;
+                let row = &mut self.cells[y as usize];
+                row[x as usize] = Cell { c, style };
+                if width == 2 {
+                    row[x as usize + 1] = Cell {
+                        c: WIDE_CHAR_TAIL,
+                        style,
+                    };
+                }
+            }
+
+            self.cursor.1 += width;
+        }
+    }
+
+    /// Read a CSI escape sequence like `\x1b[38;2;255;0;0m`, the `\x1b` being
+    /// already consumed.
+    fn read_escape_sequence<I: Iterator<Item = char>>(&mut self, chars: &mut I) {
+        if chars.next() != Some('[') {
+            return;
+        }
+
+        // The parameters end with a char between `@` and `~`.
+        let mut params = String::new();
+        for c in chars {
+            if ('@'..='~').contains(&c) {
+                if c == 'm' {
+                    self.set_graphic_rendition(&params);
+                }
+                return;
+            }
+            params.push(c);
+        }
+    }
+
+    /// Apply the parameters of a SGR escape sequence to the pen.
+    fn set_graphic_rendition(&mut self, params: &str) {
+        let mut params = params.split(';').map(|param| param.parse::<u8>().ok());
+
+        while let Some(param) = params.next() {
+            match param {
+                None | Some(0) => self.pen = CellStyle::default(),
+                Some(3) => self.pen.italic = true,
+                Some(23) => self.pen.italic = false,
+                Some(38) => self.pen.foreground = read_rgb(&mut params),
+                Some(39) => self.pen.foreground = None,
+                Some(48) => self.pen.background = read_rgb(&mut params),
+                Some(49) => self.pen.background = None,
+                Some(param) => debug!("unhandled graphic rendition: {}", param),
+            }
+        }
+    }
+
+    /// Give the text of the screen, one line per row without the trailing
+    /// spaces.
+    pub fn text(&self) -> String {
+        self.cells
+            .iter()
+            .map(|row| {
+                let line: String = row
+                    .iter()
+                    .map(|cell| cell.c)
+                    .filter(|c| *c != WIDE_CHAR_TAIL)
+                    .collect();
+                line.trim_end().to_owned()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// Read the `2;r;g;b` parameters following a 38 or a 48 one. The other
+/// color modes fall back to the default color.
+fn read_rgb<I: Iterator<Item = Option<u8>>>(params: &mut I) -> Option<(u8, u8, u8)> {
+    match params.next() {
+        Some(Some(2)) => match (params.next(), params.next(), params.next()) {
+            (Some(Some(r)), Some(Some(g)), Some(Some(b))) => Some((r, g, b)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CellStyle, Screen};
+
+    #[test]
+    fn test_put_str() {
+        let mut screen = Screen::new(6, 2);
+
+        screen.move_cursor(1, 1);
+        screen.put_str("\x1b[38;2;255;0;0mab\x1b[39m界cd");
+
+        assert_eq!("\n ab界c", screen.text());
+        assert_eq!((1, 7), screen.cursor());
+    }
+
+    #[test]
+    fn test_put_str_styles() {
+        let mut screen = Screen::new(4, 1);
+
+        screen.put_str("\x1b[48;2;1;2;3m\x1b[38;2;4;5;6ma\x1b[49mb\x1b[3mc\x1b[0md");
+
+        let style = |x| screen.cell(0, x).unwrap().style;
+        assert_eq!(
+            CellStyle {
+                foreground: Some((4, 5, 6)),
+                background: Some((1, 2, 3)),
+                italic: false,
+            },
+            style(0)
+        );
+        assert_eq!(None, style(1).background);
+        assert_eq!(Some((4, 5, 6)), style(1).foreground);
+        assert!(style(2).italic);
+        assert_eq!(CellStyle::default(), style(3));
+    }
+}
@@ -0,0 +1,54 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::screen::Screen;
+use crate::event_controller::window::{Window, WindowPosition, WindowSize};
+
+pub struct MemoryWindow {
+    screen: Rc<RefCell<Screen>>,
+    size: WindowSize,
+    pos: WindowPosition,
+}
+
+impl MemoryWindow {
+    /// Create a new window at the given position with the given size.
+    pub fn new(screen: Rc<RefCell<Screen>>, pos: WindowPosition, size: WindowSize) -> Self {
+        Self { screen, pos, size }
+    }
+}
+
+impl Window for MemoryWindow {
+    fn get_size(&self) -> WindowSize {
+        self.size
+    }
+
+    fn move_cursor(&self, y: u32, x: u32) {
+        self.screen
+            .borrow_mut()
+            .move_cursor(self.pos.y + y, self.pos.x + x);
+    }
+
+    fn save_cursor_pos(&self) {
+        self.screen.borrow_mut().save_cursor();
+    }
+
+    fn restore_cursor_pos(&self) {
+        self.screen.borrow_mut().restore_cursor();
+    }
+
+    fn move_cursor_and_clear_line(&self, line: u32) {
+        let mut screen = self.screen.borrow_mut();
+
+        // Only the part of the line inside the window is cleared in order to
+        // keep the windows on its sides.
+        screen.move_cursor(self.pos.y + line, self.pos.x);
+        screen.put_str(&" ".repeat(self.size.width as usize));
+        screen.move_cursor(self.pos.y + line, self.pos.x);
+    }
+
+    fn append_str(&self, s: &str) {
+        self.screen.borrow_mut().put_str(s);
+    }
+
+    fn refresh(&self) {}
+}
@@ -1,14 +1,19 @@
+pub mod memory;
+pub mod split;
 pub mod termion;
 
 pub use self::termion::TermionLayout;
 
-#[derive(Debug, Copy, Clone)]
+use super::view::ViewID;
+use crate::split::SplitTree;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct WindowPosition {
     pub y: u32,
     pub x: u32,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct WindowSize {
     pub height: u32,
     pub width: u32,
@@ -25,6 +30,18 @@ pub trait Window {
 }
 
 pub trait Layout {
+    /// Create a window taking all the space available for the views.
     fn create_view_window(&self) -> Box<dyn Window>;
+    /// Create a window for each pane of the split tree and draw the
+    /// separators between them.
+    fn create_view_windows(&self, tree: &SplitTree) -> Vec<(ViewID, Box<dyn Window>)>;
     fn create_new_status_bar_window(&self) -> Box<dyn Window>;
+    /// Create the window of the tab line, above the views.
+    fn create_tab_line_window(&self) -> Box<dyn Window>;
+    /// Show or hide the tab line. The views take its space once it is
+    /// hidden.
+    fn set_tab_line_visible(&mut self, visible: bool);
+    /// Give the new size of the screen after a resize and clear it. The
+    /// windows must be created again.
+    fn resize(&mut self, width: u32, height: u32);
 }
@@ -0,0 +1,171 @@
+use super::{WindowPosition, WindowSize};
+use crate::event_controller::view::ViewID;
+use crate::split::{SplitDirection, SplitTree};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaneGeometry {
+    pub view_id: ViewID,
+    pub pos: WindowPosition,
+    pub size: WindowSize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeparatorGeometry {
+    /// The direction of the split separated, a horizontal split having an
+    /// horizontal separator.
+    pub direction: SplitDirection,
+    pub pos: WindowPosition,
+    pub size: WindowSize,
+}
+
+impl SplitTree {
+    /// Compute the position and the size of the panes and of the separators
+    /// between them inside the given area.
+    pub fn geometry(
+        &self,
+        pos: WindowPosition,
+        size: WindowSize,
+    ) -> (Vec<PaneGeometry>, Vec<SeparatorGeometry>) {
+        let mut panes = Vec::new();
+        let mut separators = Vec::new();
+        self.compute_geometry(pos, size, &mut panes, &mut separators);
+
+        (panes, separators)
+    }
+
+    fn compute_geometry(
+        &self,
+        pos: WindowPosition,
+        size: WindowSize,
+        panes: &mut Vec<PaneGeometry>,
+        separators: &mut Vec<SeparatorGeometry>,
+    ) {
+        let (direction, ratio, first, second) = match self {
+            SplitTree::Pane(view_id) => {
+                panes.push(PaneGeometry {
+                    view_id: view_id.clone(),
+                    pos,
+                    size,
+                });
+                return;
+            }
+            SplitTree::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => (*direction, u32::from(*ratio), first, second),
+        };
+
+        let total = match direction {
+            SplitDirection::Horizontal => size.height,
+            SplitDirection::Vertical => size.width,
+        };
+
+        // A line or a column is kept for the separator.
+        let available = total.saturating_sub(1);
+        let first_len = (available * ratio / 100).max(1).min(available);
+        let second_len = available - first_len;
+
+        let (first_size, separator_pos, separator_size, second_pos, second_size) = match direction {
+            SplitDirection::Horizontal => (
+                WindowSize {
+                    height: first_len,
+                    width: size.width,
+                },
+                WindowPosition {
+                    y: pos.y + first_len,
+                    x: pos.x,
+                },
+                WindowSize {
+                    height: 1,
+                    width: size.width,
+                },
+                WindowPosition {
+                    y: pos.y + first_len + 1,
+                    x: pos.x,
+                },
+                WindowSize {
+                    height: second_len,
+                    width: size.width,
+                },
+            ),
+            SplitDirection::Vertical => (
+                WindowSize {
+                    height: size.height,
+                    width: first_len,
+                },
+                WindowPosition {
+                    y: pos.y,
+                    x: pos.x + first_len,
+                },
+                WindowSize {
+                    height: size.height,
+                    width: 1,
+                },
+                WindowPosition {
+                    y: pos.y,
+                    x: pos.x + first_len + 1,
+                },
+                WindowSize {
+                    height: size.height,
+                    width: second_len,
+                },
+            ),
+        };
+
+        first.compute_geometry(pos, first_size, panes, separators);
+        separators.push(SeparatorGeometry {
+            direction,
+            pos: separator_pos,
+            size: separator_size,
+        });
+        second.compute_geometry(second_pos, second_size, panes, separators);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geometry() {
+        // +---+---+
+        // |   | b |
+        // | a +---+
+        // |   | c |
+        // +---+---+
+        let mut tree = SplitTree::new("c");
+        tree.split("c", "a", SplitDirection::Vertical);
+        tree.split("c", "b", SplitDirection::Horizontal);
+
+        let (panes, separators) = tree.geometry(
+            WindowPosition { y: 0, x: 0 },
+            WindowSize {
+                height: 11,
+                width: 21,
+            },
+        );
+
+        let geometry = |view_id: &str, y, x, height, width| PaneGeometry {
+            view_id: view_id.to_owned(),
+            pos: WindowPosition { y, x },
+            size: WindowSize { height, width },
+        };
+
+        assert_eq!(
+            vec![
+                geometry("a", 0, 0, 11, 10),
+                geometry("b", 0, 11, 5, 10),
+                geometry("c", 6, 11, 5, 10),
+            ],
+            panes
+        );
+
+        assert_eq!(2, separators.len());
+        assert_eq!(SplitDirection::Vertical, separators[0].direction);
+        assert_eq!(10, separators[0].pos.x);
+        assert_eq!(SplitDirection::Horizontal, separators[1].direction);
+        assert_eq!(5, separators[1].pos.y);
+    }
+}
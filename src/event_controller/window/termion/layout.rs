@@ -3,18 +3,24 @@ use std::io::{stdout, Write};
 use std::rc::Rc;
 
 use super::window::TermionWindow;
+use crate::event_controller::view::ViewID;
 use crate::event_controller::window::{Layout, Window, WindowPosition, WindowSize};
+use crate::split::{SplitDirection, SplitTree};
 
-use termion::clear;
 use termion::color::DetectColors;
 use termion::raw::IntoRawMode;
 use termion::screen::AlternateScreen;
+use termion::{clear, cursor};
 
 const STATUS_HEIGHT: u32 = 1;
+const TAB_LINE_HEIGHT: u32 = 1;
+/// The size used when the size of the terminal can't be read.
+const DEFAULT_SIZE: (u16, u16) = (80, 24);
 
 pub struct TermionLayout {
     height: u32,
     width: u32,
+    tab_line_visible: bool,
     writer: Rc<RefCell<Box<dyn Write>>>,
 }
 
@@ -25,23 +31,89 @@ impl TermionLayout {
 
         write!(stdout, "{}", clear::All).unwrap();
 
-        let (width, height) = termion::terminal_size().unwrap();
+        let (width, height) = termion::terminal_size().unwrap_or(DEFAULT_SIZE);
 
         Self {
             writer: Rc::new(RefCell::new(Box::new(stdout))),
             height: u32::from(height),
             width: u32::from(width),
+            tab_line_visible: false,
         }
     }
+
+    /// Give the position and the size of the space available for the views,
+    /// between the tab line and the status bar.
+    fn view_area(&self) -> (WindowPosition, WindowSize) {
+        let top = if self.tab_line_visible {
+            TAB_LINE_HEIGHT
+        } else {
+            0
+        };
+
+        (
+            WindowPosition { y: top, x: 0 },
+            WindowSize {
+                // At least one row is left to the views on tiny terminals.
+                height: self.height.saturating_sub(STATUS_HEIGHT + top).max(1),
+                width: self.width,
+            },
+        )
+    }
 }
 
 impl Layout for TermionLayout {
     fn create_view_window(&self) -> Box<dyn Window> {
+        let (pos, size) = self.view_area();
+        let window = TermionWindow::new(self.writer.clone(), pos, size);
+
+        Box::new(window)
+    }
+
+    fn create_view_windows(&self, tree: &SplitTree) -> Vec<(ViewID, Box<dyn Window>)> {
+        let (pos, size) = self.view_area();
+        let (panes, separators) = tree.geometry(pos, size);
+
+        {
+            let mut writer = self.writer.borrow_mut();
+            for separator in separators {
+                // The Goto function is (1, 1)-based
+                let (x, y) = (separator.pos.x as u16 + 1, separator.pos.y as u16 + 1);
+                match separator.direction {
+                    SplitDirection::Horizontal => write!(
+                        writer,
+                        "{}{}",
+                        cursor::Goto(x, y),
+                        "─".repeat(separator.size.width as usize)
+                    )
+                    .unwrap(),
+                    SplitDirection::Vertical => {
+                        for line in 0..separator.size.height as u16 {
+                            write!(writer, "{}│", cursor::Goto(x, y + line)).unwrap();
+                        }
+                    }
+                }
+            }
+        }
+
+        panes
+            .into_iter()
+            .map(|pane| {
+                let window: Box<dyn Window> =
+                    Box::new(TermionWindow::new(self.writer.clone(), pane.pos, pane.size));
+                (pane.view_id, window)
+            })
+            .collect()
+    }
+
+    fn create_new_status_bar_window(&self) -> Box<dyn Window> {
         let window = TermionWindow::new(
             self.writer.clone(),
-            WindowPosition { y: 0, x: 0 },
+            WindowPosition {
+                y: self.height.saturating_sub(STATUS_HEIGHT),
+                x: 0,
+            },
             WindowSize {
-                height: self.height - STATUS_HEIGHT,
+                height: STATUS_HEIGHT,
                 width: self.width,
             },
         );
@@ -49,19 +121,27 @@ impl Layout for TermionLayout {
         Box::new(window)
     }
 
-    fn create_new_status_bar_window(&self) -> Box<dyn Window> {
+    fn create_tab_line_window(&self) -> Box<dyn Window> {
         let window = TermionWindow::new(
             self.writer.clone(),
-            WindowPosition {
-                y: self.height - STATUS_HEIGHT,
-                x: 0,
-            },
+            WindowPosition { y: 0, x: 0 },
             WindowSize {
-                height: STATUS_HEIGHT,
+                height: TAB_LINE_HEIGHT,
                 width: self.width,
             },
         );
 
         Box::new(window)
     }
+
+    fn set_tab_line_visible(&mut self, visible: bool) {
+        self.tab_line_visible = visible;
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.height = height;
+        self.width = width;
+
+        write!(self.writer.borrow_mut(), "{}", clear::All).unwrap();
+    }
 }
@@ -4,7 +4,7 @@ use std::rc::Rc;
 
 use crate::event_controller::window::{Window, WindowPosition, WindowSize};
 
-use termion::{clear, cursor};
+use termion::cursor;
 
 pub struct TermionWindow {
     writer: Rc<RefCell<Box<dyn Write>>>,
@@ -43,12 +43,16 @@ impl Window for TermionWindow {
     }
 
     fn move_cursor_and_clear_line(&self, line: u32) {
-        // The Goto function is (1, 1)-based
+        let goto = cursor::Goto((self.pos.x + 1) as u16, (self.pos.y + line + 1) as u16);
+
+        // Only the part of the line inside the window is cleared in order to
+        // keep the windows on its sides.
         write!(
             self.writer.borrow_mut(),
-            "{}{}",
-            cursor::Goto(1, (self.pos.y + line + 1) as u16),
-            clear::CurrentLine
+            "{}{}{}",
+            goto,
+            " ".repeat(self.size.width as usize),
+            goto,
         )
         .unwrap();
     }
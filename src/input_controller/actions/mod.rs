@@ -1,7 +1,8 @@
-mod rpc;
+pub mod rpc;
 
 use super::KeyStroke;
 use crate::core::ClientToClientWriter;
+use crate::split::{Direction, SplitDirection};
 
 use xi_rpc::Peer;
 
@@ -13,6 +14,103 @@ pub enum Response {
     SwitchToNormalMode,
     SwitchToVisualMode,
     SwitchToActionMode,
+    SwitchToOperatorPendingMode(Operator),
+    SwitchToCommandMode,
+    SwitchToSearchMode(SearchDirection),
+    /// Jump to the next match of the last search, or to the previous one if
+    /// `reverse` is set.
+    RepeatSearch {
+        reverse: bool,
+    },
+    Window(WindowCommand),
+    /// Go to the next tab page, or to the previous one if `reverse` is set.
+    SwitchTab {
+        reverse: bool,
+    },
+}
+
+/// A command on the panes of the screen.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum WindowCommand {
+    Split(SplitDirection),
+    Focus(Direction),
+    /// Grow the pane along the direction, or shrink it if the boolean is
+    /// false.
+    Resize(SplitDirection, bool),
+    Close,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SearchDirection {
+    Forward,
+    Backward,
+}
+
+impl SearchDirection {
+    pub fn reverse(self) -> Self {
+        match self {
+            SearchDirection::Forward => SearchDirection::Backward,
+            SearchDirection::Backward => SearchDirection::Forward,
+        }
+    }
+}
+
+/// An operator waiting for a motion to know on which text it applies.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+impl Operator {
+    /// Select the text covered by `motion` repeated `count` times then apply
+    /// the operator on it.
+    pub fn execute(
+        self,
+        motion: Action,
+        count: u32,
+        view_id: &str,
+        core: &dyn Peer,
+        front_event_writer: &mut ClientToClientWriter,
+    ) -> Response {
+        let count = match motion {
+            // A vertical motion covers the current line on top of the
+            // `count` lines it moves over.
+            Action::MoveUpAndSelect | Action::MoveDownAndSelect => count + 1,
+            _ => count,
+        };
+
+        if motion.is_linewise() {
+            // A linewise motion selects whole lines so the selection needs
+            // to start on the edge of the current line opposite to the
+            // motion.
+            rpc::move_to_line_start(view_id, core);
+            if motion.is_backward() {
+                rpc::move_down(view_id, core);
+            }
+        } else if motion == Action::SelectInnerWord {
+            // The selection grows from the start of the word.
+            rpc::move_to_word_start(view_id, core);
+        }
+
+        for _ in 0..count {
+            motion.execute(view_id, core, front_event_writer);
+        }
+
+        if motion.is_linewise() && motion.is_backward() {
+            // On the last line, the selection starts on its end instead of
+            // the start of the next line and the motion keeps this column.
+            rpc::move_to_line_start_and_select(view_id, core);
+        }
+
+        match self {
+            Operator::Delete => rpc::cute_selection(view_id, core),
+            Operator::Change if motion.is_linewise() => rpc::change_lines(view_id, core),
+            Operator::Change => rpc::change_selection(view_id, core),
+            Operator::Yank => rpc::yank_selection(view_id, core),
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -24,6 +122,7 @@ pub enum Action {
     SwitchToVisualMode,
     SwitchToActionMode,
     SwitchToNormalMode,
+    SwitchToCommandMode,
 
     MoveUp,
     MoveDown,
@@ -33,6 +132,11 @@ pub enum Action {
     MoveWordRight,
     MoveWordLeft,
 
+    MoveToLineStart,
+    MoveToLineEnd,
+    MoveToDocumentStart,
+    MoveToDocumentEnd,
+
     PageUp,
     PageDown,
 
@@ -42,6 +146,13 @@ pub enum Action {
     MoveRightAndSelect,
     MoveWordRightAndSelect,
     MoveWordLeftAndSelect,
+    MoveToLineStartAndSelect,
+    MoveToLineEndAndSelect,
+    MoveToDocumentStartAndSelect,
+    MoveToDocumentEndAndSelect,
+
+    SelectLine,
+    SelectInnerWord,
 
     YankSelection,
     DeleteSelection,
@@ -49,6 +160,10 @@ pub enum Action {
 
     Paste,
 
+    DeleteOperator,
+    ChangeOperator,
+    YankOperator,
+
     InsertLineBelow,
     InsertLineAbove,
 
@@ -58,6 +173,29 @@ pub enum Action {
     Indent,
     Outdent,
 
+    Undo,
+    Redo,
+
+    SearchForward,
+    SearchBackward,
+    FindNext,
+    FindPrevious,
+
+    SplitWindow,
+    VerticalSplitWindow,
+    FocusLeftWindow,
+    FocusDownWindow,
+    FocusUpWindow,
+    FocusRightWindow,
+    IncreaseWindowHeight,
+    DecreaseWindowHeight,
+    IncreaseWindowWidth,
+    DecreaseWindowWidth,
+    CloseWindow,
+
+    NextTab,
+    PreviousTab,
+
     // Custom for the insert mode. Not configurable
     InsertKeyStroke(KeyStroke),
 }
@@ -70,13 +208,14 @@ impl Action {
         front_event_writer: &mut ClientToClientWriter,
     ) -> Response {
         match self {
-            Action::WriteToFile => rpc::write_to_file(view_id, front_event_writer),
-            Action::Quite => rpc::quite(view_id, core),
+            Action::WriteToFile => rpc::write_to_file(view_id, None, front_event_writer),
+            Action::Quite => rpc::quite(),
 
             Action::SwitchToInsertMode => Response::SwitchToInsertMode,
             Action::SwitchToVisualMode => Response::SwitchToVisualMode,
             Action::SwitchToActionMode => Response::SwitchToActionMode,
             Action::SwitchToNormalMode => Response::SwitchToNormalMode,
+            Action::SwitchToCommandMode => Response::SwitchToCommandMode,
 
             Action::MoveUp => rpc::move_up(view_id, core),
             Action::MoveDown => rpc::move_down(view_id, core),
@@ -86,6 +225,11 @@ impl Action {
             Action::MoveWordRight => rpc::move_word_right(view_id, core),
             Action::MoveWordLeft => rpc::move_word_left(view_id, core),
 
+            Action::MoveToLineStart => rpc::move_to_line_start(view_id, core),
+            Action::MoveToLineEnd => rpc::move_to_line_end(view_id, core),
+            Action::MoveToDocumentStart => rpc::move_to_document_start(view_id, core),
+            Action::MoveToDocumentEnd => rpc::move_to_document_end(view_id, core),
+
             Action::PageUp => rpc::page_up(view_id, core),
             Action::PageDown => rpc::page_down(view_id, core),
 
@@ -95,6 +239,19 @@ impl Action {
             Action::MoveRightAndSelect => rpc::move_right_and_select(view_id, core),
             Action::MoveWordRightAndSelect => rpc::move_word_right_and_select(view_id, core),
             Action::MoveWordLeftAndSelect => rpc::move_word_left_and_select(view_id, core),
+            Action::MoveToLineStartAndSelect => rpc::move_to_line_start_and_select(view_id, core),
+            Action::MoveToLineEndAndSelect => rpc::move_to_line_end_and_select(view_id, core),
+            Action::MoveToDocumentStartAndSelect => {
+                rpc::move_to_document_start_and_select(view_id, core)
+            }
+            Action::MoveToDocumentEndAndSelect => {
+                rpc::move_to_document_end_and_select(view_id, core)
+            }
+
+            Action::SelectLine => rpc::move_down_and_select(view_id, core),
+            // The selection must start on the start of the word, see
+            // `Operator::execute`.
+            Action::SelectInnerWord => rpc::move_word_right_and_select(view_id, core),
 
             Action::YankSelection => rpc::yank_selection(view_id, core),
             Action::DeleteSelection => rpc::cute_selection(view_id, core),
@@ -102,6 +259,10 @@ impl Action {
 
             Action::Paste => rpc::paste(view_id, core),
 
+            Action::DeleteOperator => Response::SwitchToOperatorPendingMode(Operator::Delete),
+            Action::ChangeOperator => Response::SwitchToOperatorPendingMode(Operator::Change),
+            Action::YankOperator => Response::SwitchToOperatorPendingMode(Operator::Yank),
+
             Action::InsertKeyStroke(k) => rpc::insert_keystroke(view_id, k, core),
             Action::InsertLineBelow => rpc::insert_line_below(view_id, core),
             Action::InsertLineAbove => rpc::insert_line_above(view_id, core),
@@ -111,6 +272,41 @@ impl Action {
 
             Action::Indent => rpc::indent(view_id, core),
             Action::Outdent => rpc::outdent(view_id, core),
+
+            Action::Undo => rpc::undo(view_id, core),
+            Action::Redo => rpc::redo(view_id, core),
+
+            Action::SearchForward => Response::SwitchToSearchMode(SearchDirection::Forward),
+            Action::SearchBackward => Response::SwitchToSearchMode(SearchDirection::Backward),
+            Action::FindNext => Response::RepeatSearch { reverse: false },
+            Action::FindPrevious => Response::RepeatSearch { reverse: true },
+
+            Action::SplitWindow => {
+                Response::Window(WindowCommand::Split(SplitDirection::Horizontal))
+            }
+            Action::VerticalSplitWindow => {
+                Response::Window(WindowCommand::Split(SplitDirection::Vertical))
+            }
+            Action::FocusLeftWindow => Response::Window(WindowCommand::Focus(Direction::Left)),
+            Action::FocusDownWindow => Response::Window(WindowCommand::Focus(Direction::Down)),
+            Action::FocusUpWindow => Response::Window(WindowCommand::Focus(Direction::Up)),
+            Action::FocusRightWindow => Response::Window(WindowCommand::Focus(Direction::Right)),
+            Action::IncreaseWindowHeight => {
+                Response::Window(WindowCommand::Resize(SplitDirection::Horizontal, true))
+            }
+            Action::DecreaseWindowHeight => {
+                Response::Window(WindowCommand::Resize(SplitDirection::Horizontal, false))
+            }
+            Action::IncreaseWindowWidth => {
+                Response::Window(WindowCommand::Resize(SplitDirection::Vertical, true))
+            }
+            Action::DecreaseWindowWidth => {
+                Response::Window(WindowCommand::Resize(SplitDirection::Vertical, false))
+            }
+            Action::CloseWindow => Response::Window(WindowCommand::Close),
+
+            Action::NextTab => Response::SwitchTab { reverse: false },
+            Action::PreviousTab => Response::SwitchTab { reverse: true },
         }
     }
 
@@ -123,6 +319,7 @@ impl Action {
             "switch_to_visual_mode" => Some(Action::SwitchToVisualMode),
             "switch_to_action_mode" => Some(Action::SwitchToActionMode),
             "switch_to_normal_mode" => Some(Action::SwitchToNormalMode),
+            "switch_to_command_mode" => Some(Action::SwitchToCommandMode),
 
             "move_up" => Some(Action::MoveUp),
             "move_down" => Some(Action::MoveDown),
@@ -131,10 +328,22 @@ impl Action {
             "page_up" => Some(Action::PageUp),
             "page_down" => Some(Action::PageDown),
 
+            "move_to_line_start" => Some(Action::MoveToLineStart),
+            "move_to_line_end" => Some(Action::MoveToLineEnd),
+            "move_to_document_start" => Some(Action::MoveToDocumentStart),
+            "move_to_document_end" => Some(Action::MoveToDocumentEnd),
+
             "move_up_and_select" => Some(Action::MoveUpAndSelect),
             "move_down_and_select" => Some(Action::MoveDownAndSelect),
             "move_left_and_select" => Some(Action::MoveLeftAndSelect),
             "move_right_and_select" => Some(Action::MoveRightAndSelect),
+            "move_to_line_start_and_select" => Some(Action::MoveToLineStartAndSelect),
+            "move_to_line_end_and_select" => Some(Action::MoveToLineEndAndSelect),
+            "move_to_document_start_and_select" => Some(Action::MoveToDocumentStartAndSelect),
+            "move_to_document_end_and_select" => Some(Action::MoveToDocumentEndAndSelect),
+
+            "select_line" => Some(Action::SelectLine),
+            "select_inner_word" => Some(Action::SelectInnerWord),
 
             "yank_selection" => Some(Action::YankSelection),
             "delete_selection" => Some(Action::DeleteSelection),
@@ -142,6 +351,10 @@ impl Action {
 
             "paste" => Some(Action::Paste),
 
+            "delete_operator" => Some(Action::DeleteOperator),
+            "change_operator" => Some(Action::ChangeOperator),
+            "yank_operator" => Some(Action::YankOperator),
+
             "insert_line_below" => Some(Action::InsertLineBelow),
             "insert_line_above" => Some(Action::InsertLineAbove),
 
@@ -151,7 +364,110 @@ impl Action {
             "indent" => Some(Action::Indent),
             "outdent" => Some(Action::Outdent),
 
+            "undo" => Some(Action::Undo),
+            "redo" => Some(Action::Redo),
+
+            "search_forward" => Some(Action::SearchForward),
+            "search_backward" => Some(Action::SearchBackward),
+            "find_next" => Some(Action::FindNext),
+            "find_previous" => Some(Action::FindPrevious),
+
+            "split_window" => Some(Action::SplitWindow),
+            "vertical_split_window" => Some(Action::VerticalSplitWindow),
+            "focus_left_window" => Some(Action::FocusLeftWindow),
+            "focus_down_window" => Some(Action::FocusDownWindow),
+            "focus_up_window" => Some(Action::FocusUpWindow),
+            "focus_right_window" => Some(Action::FocusRightWindow),
+            "increase_window_height" => Some(Action::IncreaseWindowHeight),
+            "decrease_window_height" => Some(Action::DecreaseWindowHeight),
+            "increase_window_width" => Some(Action::IncreaseWindowWidth),
+            "decrease_window_width" => Some(Action::DecreaseWindowWidth),
+            "close_window" => Some(Action::CloseWindow),
+
+            "next_tab" => Some(Action::NextTab),
+            "previous_tab" => Some(Action::PreviousTab),
+
             _ => None,
         }
     }
+
+    /// Indicate if the action selects whole lines when used as an operator
+    /// motion.
+    pub fn is_linewise(self) -> bool {
+        matches!(
+            self,
+            Action::SelectLine
+                | Action::MoveUpAndSelect
+                | Action::MoveDownAndSelect
+                | Action::MoveToDocumentStartAndSelect
+                | Action::MoveToDocumentEndAndSelect
+        )
+    }
+
+    /// Indicate if the motion goes toward the start of the document.
+    fn is_backward(self) -> bool {
+        matches!(
+            self,
+            Action::MoveUpAndSelect | Action::MoveToDocumentStartAndSelect
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use serde_json::Value;
+    use xi_rpc::{Callback, Error, Peer};
+
+    use super::{Action, Operator, Response};
+    use crate::core;
+    use crate::input_controller::PASTE_BUFFER;
+
+    /// A core answering null to every request, like the "copy" of an empty
+    /// selection.
+    #[derive(Clone)]
+    struct NullCore;
+
+    impl Peer for NullCore {
+        fn box_clone(&self) -> Box<dyn Peer> {
+            Box::new(self.clone())
+        }
+
+        fn send_rpc_notification(&self, _method: &str, _params: &Value) {}
+
+        fn send_rpc_request_async(&self, _method: &str, _params: &Value, f: Box<dyn Callback>) {
+            f.call(Ok(Value::Null));
+        }
+
+        fn send_rpc_request(&self, _method: &str, _params: &Value) -> Result<Value, Error> {
+            Ok(Value::Null)
+        }
+
+        fn request_is_pending(&self) -> bool {
+            false
+        }
+
+        fn schedule_idle(&self, _token: usize) {}
+
+        fn schedule_timer(&self, _after: Instant, _token: usize) {}
+    }
+
+    #[test]
+    fn test_yank_an_empty_motion() {
+        let (mut writer, _reader) = core::client_channel();
+        *PASTE_BUFFER.lock().unwrap() = Some(String::from("kept"));
+
+        // Like `yh` at the start of a line.
+        let response = Operator::Yank.execute(
+            Action::MoveLeftAndSelect,
+            1,
+            "view-id-1",
+            &NullCore,
+            &mut writer,
+        );
+
+        assert_eq!(Response::SwitchToNormalMode, response);
+        assert_eq!(Some(String::from("kept")), *PASTE_BUFFER.lock().unwrap());
+    }
 }
@@ -1,14 +1,20 @@
-use crate::core::ClientToClientWriter;
+use crate::core::{ClientMessage, ClientToClientWriter, Substitution};
+use crate::input_controller::actions::SearchDirection;
 use crate::input_controller::keyboard::KeyStroke;
 use crate::input_controller::{Response, PASTE_BUFFER};
 
+use serde_json::Value;
 use xi_rpc::Peer;
 
 pub fn insert_keystroke(view_id: &str, key: KeyStroke, core: &dyn Peer) -> Response {
     let output = match key {
         KeyStroke::Char(c) => c.to_string(),
         KeyStroke::KeySpace => ' '.to_string(),
-        _ => String::from("<?>"),
+        // Let the core handle the auto-indentation.
+        KeyStroke::KeyEnter => return insert_newline(view_id, core),
+        KeyStroke::KeyTab => return insert_tab(view_id, core),
+        // The other keys have no text to insert.
+        _ => return Response::Continue,
     };
 
     core.send_rpc_notification(
@@ -24,19 +30,73 @@ pub fn insert_keystroke(view_id: &str, key: KeyStroke, core: &dyn Peer) -> Respo
     Response::Continue
 }
 
-pub fn quite(view_id: &str, core: &dyn Peer) -> Response {
-    core.send_rpc_notification("close_view", &json!({ "view_id": view_id }));
-    core.send_rpc_notification("exit", &json!({}));
-
+/// Stop the client.
+///
+/// The views are closed once the pending writes are done, during the
+/// shutdown.
+pub fn quite() -> Response {
     Response::Stop
 }
 
-pub fn write_to_file(view_id: &str, core: &mut ClientToClientWriter) -> Response {
-    core.send_rpc_notification("write_to_file", &json!({ "view_id": view_id }));
+/// Write the view content into its file.
+///
+/// If `file_path` is set, the view is saved under this new path.
+pub fn write_to_file(
+    view_id: &str,
+    file_path: Option<&str>,
+    core: &mut ClientToClientWriter,
+) -> Response {
+    core.send(ClientMessage::WriteToFile {
+        view_id: view_id.to_owned(),
+        file_path: file_path.map(str::to_owned),
+    });
+
+    Response::SwitchToNormalMode
+}
+
+/// Ask the client to replace the matches of the substitution.
+///
+/// The client drives the find and replace queries of the core because it
+/// knows the cursor line.
+pub fn substitute(
+    view_id: &str,
+    substitution: &Substitution,
+    core: &mut ClientToClientWriter,
+) -> Response {
+    core.send(ClientMessage::Substitute {
+        view_id: view_id.to_owned(),
+        substitution: substitution.clone(),
+    });
 
     Response::SwitchToNormalMode
 }
 
+pub fn goto_line(view_id: &str, line: u64, core: &dyn Peer) -> Response {
+    core.send_rpc_notification(
+        "edit",
+        &json!({
+            "method": "goto_line",
+            "view_id": view_id,
+            "params": {
+                "line": line,
+            }
+        }),
+    );
+    Response::Continue
+}
+
+/// Change a configuration value for the given view only.
+pub fn set_config(view_id: &str, key: &str, value: &Value, core: &dyn Peer) -> Response {
+    core.send_rpc_notification(
+        "modify_user_config",
+        &json!({
+            "domain": { "user_override": view_id },
+            "changes": { key: value },
+        }),
+    );
+    Response::Continue
+}
+
 pub fn delete_backward(view_id: &str, core: &dyn Peer) -> Response {
     core.send_rpc_notification(
         "edit",
@@ -108,6 +168,38 @@ pub fn move_word_left(view_id: &str, core: &dyn Peer) -> Response {
     Response::Continue
 }
 
+pub fn move_to_line_start(view_id: &str, core: &dyn Peer) -> Response {
+    core.send_rpc_notification(
+        "edit",
+        &json!({ "method": "move_to_left_end_of_line", "view_id": view_id}),
+    );
+    Response::Continue
+}
+
+pub fn move_to_line_end(view_id: &str, core: &dyn Peer) -> Response {
+    core.send_rpc_notification(
+        "edit",
+        &json!({ "method": "move_to_right_end_of_line", "view_id": view_id}),
+    );
+    Response::Continue
+}
+
+pub fn move_to_document_start(view_id: &str, core: &dyn Peer) -> Response {
+    core.send_rpc_notification(
+        "edit",
+        &json!({ "method": "move_to_beginning_of_document", "view_id": view_id}),
+    );
+    Response::Continue
+}
+
+pub fn move_to_document_end(view_id: &str, core: &dyn Peer) -> Response {
+    core.send_rpc_notification(
+        "edit",
+        &json!({ "method": "move_to_end_of_document", "view_id": view_id}),
+    );
+    Response::Continue
+}
+
 pub fn move_up_and_select(view_id: &str, core: &dyn Peer) -> Response {
     core.send_rpc_notification(
         "edit",
@@ -156,6 +248,45 @@ pub fn move_word_left_and_select(view_id: &str, core: &dyn Peer) -> Response {
     Response::Continue
 }
 
+pub fn move_to_line_start_and_select(view_id: &str, core: &dyn Peer) -> Response {
+    core.send_rpc_notification(
+        "edit",
+        &json!({ "method": "move_to_left_end_of_line_and_modify_selection", "view_id": view_id}),
+    );
+    Response::Continue
+}
+
+pub fn move_to_line_end_and_select(view_id: &str, core: &dyn Peer) -> Response {
+    core.send_rpc_notification(
+        "edit",
+        &json!({ "method": "move_to_right_end_of_line_and_modify_selection", "view_id": view_id}),
+    );
+    Response::Continue
+}
+
+pub fn move_to_document_start_and_select(view_id: &str, core: &dyn Peer) -> Response {
+    core.send_rpc_notification(
+        "edit",
+        &json!({ "method": "move_to_beginning_of_document_and_modify_selection", "view_id": view_id}),
+    );
+    Response::Continue
+}
+
+pub fn move_to_document_end_and_select(view_id: &str, core: &dyn Peer) -> Response {
+    core.send_rpc_notification(
+        "edit",
+        &json!({ "method": "move_to_end_of_document_and_modify_selection", "view_id": view_id}),
+    );
+    Response::Continue
+}
+
+/// Move the cursor to the start of the word under it.
+pub fn move_to_word_start(view_id: &str, core: &dyn Peer) -> Response {
+    // Step inside the word first, the cursor may already be on its start.
+    move_right(view_id, core);
+    move_word_left(view_id, core)
+}
+
 pub fn insert_newline(view_id: &str, core: &dyn Peer) -> Response {
     core.send_rpc_notification(
         "edit",
@@ -164,13 +295,24 @@ pub fn insert_newline(view_id: &str, core: &dyn Peer) -> Response {
     Response::Continue
 }
 
+pub fn insert_tab(view_id: &str, core: &dyn Peer) -> Response {
+    core.send_rpc_notification(
+        "edit",
+        &json!({ "method": "insert_tab", "view_id": view_id}),
+    );
+    Response::Continue
+}
+
 pub fn yank_selection(view_id: &str, core: &dyn Peer) -> Response {
     let res = core.send_rpc_request("edit", &json!({ "method": "copy", "view_id": view_id}));
-    if let Ok(paste_buffer) = res {
-        let mut buffer = PASTE_BUFFER.lock().unwrap();
-        *buffer = Some(String::from(paste_buffer.as_str().unwrap()));
-    } else {
-        error!("failed to copy selection: {:?}", res.unwrap_err());
+    match res {
+        Ok(Value::String(copy)) => {
+            let mut buffer = PASTE_BUFFER.lock().unwrap();
+            *buffer = Some(copy);
+        }
+        // The core returns null when the selection is empty.
+        Ok(_) => (),
+        Err(err) => error!("failed to copy selection: {:?}", err),
     }
 
     // Remove the selection
@@ -183,13 +325,7 @@ pub fn yank_selection(view_id: &str, core: &dyn Peer) -> Response {
 }
 
 pub fn cute_selection(view_id: &str, core: &dyn Peer) -> Response {
-    let cut_res = core.send_rpc_request("edit", &json!({ "method": "cut", "view_id": view_id}));
-    if cut_res.is_err() {
-        error!("failed to cut the selection: {:?}", cut_res);
-    }
-
-    let mut buffer = PASTE_BUFFER.lock().unwrap();
-    *buffer = Some(String::from(cut_res.unwrap().as_str().unwrap()));
+    cut_into_paste_buffer(view_id, core);
 
     // Remove the selection
     core.send_rpc_notification(
@@ -200,6 +336,21 @@ pub fn cute_selection(view_id: &str, core: &dyn Peer) -> Response {
     Response::SwitchToNormalMode
 }
 
+pub fn change_selection(view_id: &str, core: &dyn Peer) -> Response {
+    cut_into_paste_buffer(view_id, core);
+
+    Response::SwitchToInsertMode
+}
+
+/// Cut the selected lines and leave an empty line in their place, like
+/// `cc`.
+pub fn change_lines(view_id: &str, core: &dyn Peer) -> Response {
+    cut_into_paste_buffer(view_id, core);
+
+    // The cut takes the newline of the last line, give it back.
+    insert_line_above(view_id, core)
+}
+
 pub fn cute_selection_and_paste(view_id: &str, core: &dyn Peer) -> Response {
     let cut_res = core.send_rpc_request("edit", &json!({ "method": "cut", "view_id": view_id}));
     if cut_res.is_err() {
@@ -208,8 +359,11 @@ pub fn cute_selection_and_paste(view_id: &str, core: &dyn Peer) -> Response {
 
     paste(view_id, core);
 
-    let mut buffer = PASTE_BUFFER.lock().unwrap();
-    *buffer = Some(String::from(cut_res.unwrap().as_str().unwrap()));
+    // The core returns null when the selection is empty.
+    if let Some(cut) = cut_res.ok().as_ref().and_then(Value::as_str) {
+        let mut buffer = PASTE_BUFFER.lock().unwrap();
+        *buffer = Some(String::from(cut));
+    }
 
     // Remove the selection
     core.send_rpc_notification(
@@ -220,6 +374,19 @@ pub fn cute_selection_and_paste(view_id: &str, core: &dyn Peer) -> Response {
     Response::SwitchToNormalMode
 }
 
+fn cut_into_paste_buffer(view_id: &str, core: &dyn Peer) {
+    let cut_res = core.send_rpc_request("edit", &json!({ "method": "cut", "view_id": view_id}));
+    match cut_res {
+        Ok(Value::String(cut)) => {
+            let mut buffer = PASTE_BUFFER.lock().unwrap();
+            *buffer = Some(cut);
+        }
+        // The core returns null when the selection is empty.
+        Ok(_) => (),
+        Err(err) => error!("failed to cut the selection: {:?}", err),
+    }
+}
+
 pub fn paste(view_id: &str, core: &dyn Peer) -> Response {
     let buffer = PASTE_BUFFER.lock().unwrap();
     if let Some(ref s) = *buffer {
@@ -262,3 +429,72 @@ pub fn outdent(view_id: &str, core: &dyn Peer) -> Response {
     core.send_rpc_notification("edit", &json!({ "method": "outdent", "view_id": view_id}));
     Response::Continue
 }
+
+pub fn undo(view_id: &str, core: &dyn Peer) -> Response {
+    core.send_rpc_notification("edit", &json!({ "method": "undo", "view_id": view_id}));
+    Response::Continue
+}
+
+pub fn redo(view_id: &str, core: &dyn Peer) -> Response {
+    core.send_rpc_notification("edit", &json!({ "method": "redo", "view_id": view_id}));
+    Response::Continue
+}
+
+/// Search `chars` inside the view.
+///
+/// A `None` value removes the current search.
+pub fn find(view_id: &str, chars: Option<&str>, core: &dyn Peer) -> Response {
+    core.send_rpc_notification(
+        "edit",
+        &json!({
+            "method": "find",
+            "view_id": view_id,
+            "params": {
+                "chars": chars,
+                "case_sensitive": true,
+                "regex": false,
+                "whole_words": false,
+            }
+        }),
+    );
+
+    core.send_rpc_notification(
+        "edit",
+        &json!({
+            "method": "highlight_find",
+            "view_id": view_id,
+            "params": { "visible": chars.is_some() }
+        }),
+    );
+
+    Response::Continue
+}
+
+/// Move the cursor at the start of the next match of the current search in
+/// the given direction.
+pub fn find_next(view_id: &str, direction: SearchDirection, core: &dyn Peer) -> Response {
+    let method = match direction {
+        // Step over the match under the cursor.
+        SearchDirection::Forward => {
+            move_right(view_id, core);
+            "find_next"
+        }
+        SearchDirection::Backward => "find_previous",
+    };
+
+    core.send_rpc_notification(
+        "edit",
+        &json!({
+            "method": method,
+            "view_id": view_id,
+            "params": {
+                "wrap_around": true,
+                "allow_same": false,
+                "modify_selection": "set",
+            }
+        }),
+    );
+
+    // The match is selected, collapse the selection on its start.
+    move_left(view_id, core)
+}
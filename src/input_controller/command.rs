@@ -0,0 +1,380 @@
+use crate::core::Substitution;
+
+use failure::Error;
+use serde_json::Value;
+
+/// An ex-style command typed into the command line, like `:w` or `:q!`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Write the file, optionally under a new path.
+    Write(Option<String>),
+    Quit {
+        force: bool,
+    },
+    WriteAndQuit,
+    /// Open a file in a new buffer, or switch to its buffer if it is already
+    /// opened.
+    Edit(String),
+    /// Switch to the next buffer.
+    NextBuffer,
+    /// Switch to the previous buffer.
+    PreviousBuffer,
+    /// Switch to the buffer with the given number, starting at 1.
+    GotoBuffer(usize),
+    ListBuffers,
+    /// Close the current buffer without quitting.
+    DeleteBuffer {
+        force: bool,
+    },
+    /// Split the current pane horizontally, optionally on an another file.
+    Split(Option<String>),
+    /// Split the current pane vertically, optionally on an another file.
+    VerticalSplit(Option<String>),
+    /// Close the current pane.
+    Close,
+    /// Open a new tab page, optionally on an another file.
+    TabNew(Option<String>),
+    TabClose,
+    TabNext,
+    TabPrevious,
+    /// Move the cursor to the given line number, starting at 1.
+    GotoLine(u64),
+    /// Change a value of the xi-core configuration.
+    Set {
+        key: String,
+        value: Value,
+    },
+    /// Replace the matches of a pattern, like `:s/foo/bar/g`.
+    Substitute(Substitution),
+}
+
+impl Command {
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        let input = input.trim();
+
+        if let Ok(line) = input.parse::<u64>() {
+            return Ok(Command::GotoLine(line));
+        }
+
+        if let Some(substitution) = parse_substitute(input)? {
+            return Ok(Command::Substitute(substitution));
+        }
+
+        let (name, args) = match input.find(char::is_whitespace) {
+            Some(idx) => (&input[..idx], input[idx..].trim()),
+            None => (input, ""),
+        };
+
+        match name {
+            "w" | "write" => Ok(Command::Write(non_empty(args))),
+            "q" | "quit" | "q!" | "quit!" => {
+                ensure_no_args(args)?;
+                Ok(Command::Quit {
+                    force: name.ends_with('!'),
+                })
+            }
+            "wq" | "x" => {
+                ensure_no_args(args)?;
+                Ok(Command::WriteAndQuit)
+            }
+            "e" | "edit" => match non_empty(args) {
+                Some(path) => Ok(Command::Edit(path)),
+                None => Err(format_err!("Argument required")),
+            },
+            "bn" | "bnext" => {
+                ensure_no_args(args)?;
+                Ok(Command::NextBuffer)
+            }
+            "bp" | "bprevious" | "bN" | "bNext" => {
+                ensure_no_args(args)?;
+                Ok(Command::PreviousBuffer)
+            }
+            "b" | "buffer" => match args.parse::<usize>() {
+                Ok(number) => Ok(Command::GotoBuffer(number)),
+                Err(_) if args.is_empty() => Err(format_err!("Argument required")),
+                Err(_) => Err(format_err!("Invalid buffer number: {}", args)),
+            },
+            "ls" | "buffers" => {
+                ensure_no_args(args)?;
+                Ok(Command::ListBuffers)
+            }
+            "bd" | "bdelete" | "bd!" | "bdelete!" => {
+                ensure_no_args(args)?;
+                Ok(Command::DeleteBuffer {
+                    force: name.ends_with('!'),
+                })
+            }
+            "sp" | "split" => Ok(Command::Split(non_empty(args))),
+            "vs" | "vsplit" => Ok(Command::VerticalSplit(non_empty(args))),
+            "clo" | "close" => {
+                ensure_no_args(args)?;
+                Ok(Command::Close)
+            }
+            "tabnew" | "tabe" | "tabedit" => Ok(Command::TabNew(non_empty(args))),
+            "tabc" | "tabclose" => {
+                ensure_no_args(args)?;
+                Ok(Command::TabClose)
+            }
+            "tabn" | "tabnext" => {
+                ensure_no_args(args)?;
+                Ok(Command::TabNext)
+            }
+            "tabp" | "tabprevious" | "tabN" | "tabNext" => {
+                ensure_no_args(args)?;
+                Ok(Command::TabPrevious)
+            }
+            "set" => parse_set(args),
+            _ => Err(format_err!("Not an editor command: {}", input)),
+        }
+    }
+}
+
+fn non_empty(args: &str) -> Option<String> {
+    if args.is_empty() {
+        None
+    } else {
+        Some(args.to_owned())
+    }
+}
+
+fn ensure_no_args(args: &str) -> Result<(), Error> {
+    if args.is_empty() {
+        Ok(())
+    } else {
+        Err(format_err!("Trailing characters: {}", args))
+    }
+}
+
+/// Parse the `key=value` argument of a `:set` command.
+///
+/// A key without value is set to `true`.
+fn parse_set(args: &str) -> Result<Command, Error> {
+    if args.is_empty() {
+        return Err(format_err!("Argument required"));
+    }
+
+    let (key, value) = match args.find('=') {
+        Some(idx) => (args[..idx].trim(), parse_value(args[idx + 1..].trim())),
+        None => (args, Value::Bool(true)),
+    };
+
+    Ok(Command::Set {
+        key: key.to_owned(),
+        value,
+    })
+}
+
+/// Parse a `[%]s/pattern/replacement/[flags]` command.
+///
+/// Any punctuation character can be used as delimiter in place of `/`. It
+/// returns `None` if the input is not a substitute command.
+///
+/// The pattern is a regex, a `\V` inside it makes it a literal string and a
+/// `\c` or a `\C` makes it case insensitive or sensitive. The flags are `g`
+/// for all the matches of the line, `i` to ignore the case and `I` to not
+/// ignore it.
+fn parse_substitute(input: &str) -> Result<Option<Substitution>, Error> {
+    let (whole_file, rest) = if input.starts_with('%') {
+        (true, &input[1..])
+    } else {
+        (false, input)
+    };
+
+    let rest = if rest.starts_with("substitute") {
+        &rest["substitute".len()..]
+    } else if rest.starts_with('s') {
+        &rest[1..]
+    } else {
+        return Ok(None);
+    };
+
+    let delimiter = match rest.chars().next() {
+        Some(c) if !c.is_alphanumeric() && !c.is_whitespace() && c != '\\' && c != '"' => c,
+        _ => return Ok(None),
+    };
+
+    let mut parts = split_escaped(&rest[delimiter.len_utf8()..], delimiter).into_iter();
+    let raw_pattern = parts.next().unwrap_or_default();
+    let replacement = parts.next().unwrap_or_default();
+    let flags = parts.next().unwrap_or_default();
+    if let Some(part) = parts.next() {
+        return Err(format_err!("Trailing characters: {}", part));
+    }
+
+    let mut substitution = Substitution {
+        pattern: String::with_capacity(raw_pattern.len()),
+        replacement,
+        whole_file,
+        global: false,
+        case_sensitive: true,
+        regex: true,
+    };
+
+    let mut chars = raw_pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            substitution.pattern.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('c') => substitution.case_sensitive = false,
+            Some('C') => substitution.case_sensitive = true,
+            Some('V') => substitution.regex = false,
+            Some(next) => {
+                substitution.pattern.push(c);
+                substitution.pattern.push(next);
+            }
+            None => substitution.pattern.push(c),
+        }
+    }
+
+    for flag in flags.trim().chars() {
+        match flag {
+            'g' => substitution.global = true,
+            'i' => substitution.case_sensitive = false,
+            'I' => substitution.case_sensitive = true,
+            _ => return Err(format_err!("Trailing characters: {}", flags)),
+        }
+    }
+
+    Ok(Some(substitution))
+}
+
+/// Split `input` on each `delimiter` which is not escaped by a backslash.
+fn split_escaped(input: &str, delimiter: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        if c == delimiter {
+            parts.push(String::new());
+            continue;
+        }
+
+        let part = parts.last_mut().unwrap();
+        if c == '\\' {
+            match chars.next() {
+                Some(next) if next == delimiter => part.push(next),
+                Some(next) => {
+                    part.push(c);
+                    part.push(next);
+                }
+                None => part.push(c),
+            }
+        } else {
+            part.push(c);
+        }
+    }
+
+    parts
+}
+
+fn parse_value(raw: &str) -> Value {
+    if let Ok(boolean) = raw.parse::<bool>() {
+        return Value::Bool(boolean);
+    }
+
+    if let Ok(number) = raw.parse::<u64>() {
+        return json!(number);
+    }
+
+    Value::String(raw.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Command, Substitution};
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(Command::Write(None), Command::parse("w").unwrap());
+        assert_eq!(
+            Command::Write(Some(String::from("foo.txt"))),
+            Command::parse("w foo.txt").unwrap()
+        );
+        assert_eq!(Command::Quit { force: true }, Command::parse("q!").unwrap());
+        assert_eq!(Command::WriteAndQuit, Command::parse(" wq ").unwrap());
+        assert_eq!(Command::GotoLine(42), Command::parse("42").unwrap());
+        assert_eq!(
+            Command::Edit(String::from("foo.txt")),
+            Command::parse("e foo.txt").unwrap()
+        );
+        assert_eq!(Command::NextBuffer, Command::parse("bn").unwrap());
+        assert_eq!(
+            Command::VerticalSplit(Some(String::from("foo.txt"))),
+            Command::parse("vs foo.txt").unwrap()
+        );
+        assert_eq!(Command::TabNew(None), Command::parse("tabnew").unwrap());
+        assert_eq!(Command::GotoBuffer(2), Command::parse("b 2").unwrap());
+        assert_eq!(
+            Command::DeleteBuffer { force: true },
+            Command::parse("bd!").unwrap()
+        );
+        assert_eq!(
+            Command::Set {
+                key: String::from("tab_size"),
+                value: json!(2),
+            },
+            Command::parse("set tab_size=2").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_commands() {
+        assert!(Command::parse("foo").is_err());
+        assert!(Command::parse("e").is_err());
+        assert!(Command::parse("q foo").is_err());
+        assert!(Command::parse("b foo").is_err());
+        assert!(Command::parse("s/foo/bar/x").is_err());
+        assert!(Command::parse("s/foo/bar/g/baz").is_err());
+    }
+
+    #[test]
+    fn test_parse_substitute_commands() {
+        assert_eq!(
+            Command::Substitute(Substitution {
+                pattern: String::from("fo+"),
+                replacement: String::from("bar"),
+                whole_file: false,
+                global: false,
+                case_sensitive: true,
+                regex: true,
+            }),
+            Command::parse("s/fo+/bar").unwrap()
+        );
+
+        assert_eq!(
+            Command::Substitute(Substitution {
+                pattern: String::from("a/b"),
+                replacement: String::from("c"),
+                whole_file: true,
+                global: true,
+                case_sensitive: false,
+                regex: false,
+            }),
+            Command::parse("%s/\\Va\\/b/c/gi").unwrap()
+        );
+
+        assert_eq!(
+            Command::Substitute(Substitution {
+                pattern: String::from("foo"),
+                replacement: String::new(),
+                whole_file: false,
+                global: false,
+                case_sensitive: false,
+                regex: true,
+            }),
+            Command::parse("s#foo\\c#").unwrap()
+        );
+
+        // The `:set` command is not a substitution.
+        assert_eq!(
+            Command::Set {
+                key: String::from("wrap"),
+                value: json!(true),
+            },
+            Command::parse("set wrap").unwrap()
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use super::keyboard::KeyStroke;
+
+/// The result of a keystroke inside the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandLineEvent {
+    /// The content or the cursor of the command line has changed.
+    Changed,
+    /// The user has validated the given content.
+    Validated(String),
+    /// The user has left the command line without validating it.
+    Canceled,
+}
+
+/// A single line editor with an history, used by the prompts displayed in
+/// the status bar.
+#[derive(Debug, Default)]
+pub struct CommandLine {
+    content: Vec<char>,
+    /// The position of the cursor, as a number of chars.
+    cursor: usize,
+    history: Vec<String>,
+    /// The index of the history entry currently displayed.
+    history_idx: Option<usize>,
+}
+
+impl CommandLine {
+    /// Clear the line in order to type a new command.
+    pub fn start(&mut self) {
+        self.content.clear();
+        self.cursor = 0;
+        self.history_idx = None;
+    }
+
+    pub fn content(&self) -> String {
+        self.content.iter().collect()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn handle_keystroke(&mut self, key: KeyStroke) -> CommandLineEvent {
+        match key {
+            KeyStroke::Char(c) => self.insert(c),
+            KeyStroke::KeySpace => self.insert(' '),
+            KeyStroke::KeyBackSpace if self.content.is_empty() => {
+                return CommandLineEvent::Canceled
+            }
+            KeyStroke::KeyBackSpace => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    self.content.remove(self.cursor);
+                }
+            }
+            KeyStroke::KeyDelete => {
+                if self.cursor < self.content.len() {
+                    self.content.remove(self.cursor);
+                }
+            }
+            KeyStroke::Ctrl('u') => {
+                self.content.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            KeyStroke::KeyLeft => self.cursor = self.cursor.saturating_sub(1),
+            KeyStroke::KeyRight => self.cursor = (self.cursor + 1).min(self.content.len()),
+            KeyStroke::KeyHome | KeyStroke::Ctrl('b') => self.cursor = 0,
+            KeyStroke::KeyEnd | KeyStroke::Ctrl('e') => self.cursor = self.content.len(),
+            KeyStroke::KeyUp => self.previous_history_entry(),
+            KeyStroke::KeyDown => self.next_history_entry(),
+            KeyStroke::KeyEnter => return CommandLineEvent::Validated(self.validate()),
+            KeyStroke::KeyEscape | KeyStroke::Ctrl('c') => return CommandLineEvent::Canceled,
+            _ => (),
+        }
+
+        CommandLineEvent::Changed
+    }
+
+    fn insert(&mut self, c: char) {
+        self.content.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    fn validate(&mut self) -> String {
+        let content = self.content();
+
+        if !content.trim().is_empty() && self.history.last() != Some(&content) {
+            self.history.push(content.clone());
+        }
+
+        content
+    }
+
+    fn previous_history_entry(&mut self) {
+        let idx = match self.history_idx {
+            _ if self.history.is_empty() => return,
+            None => self.history.len() - 1,
+            Some(idx) => idx.saturating_sub(1),
+        };
+
+        self.load_history_entry(Some(idx));
+    }
+
+    fn next_history_entry(&mut self) {
+        match self.history_idx {
+            Some(idx) if idx + 1 < self.history.len() => self.load_history_entry(Some(idx + 1)),
+            // Going after the last entry gives back an empty line.
+            Some(_) => self.load_history_entry(None),
+            None => (),
+        }
+    }
+
+    fn load_history_entry(&mut self, idx: Option<usize>) {
+        self.content = match idx {
+            Some(idx) => self.history[idx].chars().collect(),
+            None => Vec::new(),
+        };
+        self.cursor = self.content.len();
+        self.history_idx = idx;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::keyboard::KeyStroke;
+    use super::{CommandLine, CommandLineEvent};
+
+    fn type_str(command_line: &mut CommandLine, input: &str) {
+        for c in input.chars() {
+            command_line.handle_keystroke(KeyStroke::Char(c));
+        }
+    }
+
+    #[test]
+    fn test_line_edition() {
+        let mut command_line = CommandLine::default();
+
+        type_str(&mut command_line, "wq");
+        command_line.handle_keystroke(KeyStroke::KeyLeft);
+        command_line.handle_keystroke(KeyStroke::KeyBackSpace);
+        type_str(&mut command_line, "e");
+
+        assert_eq!("eq", command_line.content());
+        assert_eq!(1, command_line.cursor());
+        assert_eq!(
+            CommandLineEvent::Validated(String::from("eq")),
+            command_line.handle_keystroke(KeyStroke::KeyEnter)
+        );
+    }
+
+    #[test]
+    fn test_history() {
+        let mut command_line = CommandLine::default();
+
+        for command in &["w", "q"] {
+            command_line.start();
+            type_str(&mut command_line, command);
+            command_line.handle_keystroke(KeyStroke::KeyEnter);
+        }
+
+        command_line.start();
+        command_line.handle_keystroke(KeyStroke::KeyUp);
+        command_line.handle_keystroke(KeyStroke::KeyUp);
+        assert_eq!("w", command_line.content());
+
+        command_line.handle_keystroke(KeyStroke::KeyDown);
+        assert_eq!("q", command_line.content());
+
+        command_line.handle_keystroke(KeyStroke::KeyDown);
+        assert_eq!("", command_line.content());
+    }
+}
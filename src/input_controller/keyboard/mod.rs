@@ -2,8 +2,19 @@ mod termion;
 
 pub use self::termion::TermionKeyboard;
 
+use std::time::Duration;
+
 pub trait Keyboard {
+    /// Block until the next keystroke.
+    ///
+    /// A `None` value means the input is closed.
     fn get_next_keystroke(&mut self) -> Option<KeyStroke>;
+
+    /// Wait at most `timeout` for the next keystroke.
+    ///
+    /// A `None` value means no key has been pressed in time or the input is
+    /// closed.
+    fn get_next_keystroke_before(&mut self, timeout: Duration) -> Option<KeyStroke>;
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -11,6 +22,7 @@ pub enum KeyStroke {
     Char(char),
     KeyF(u8),
     Alt(char),
+    Ctrl(char),
     KeyUp,
     KeyDown,
     KeyLeft,
@@ -21,16 +33,60 @@ pub enum KeyStroke {
     KeyBackSpace,
     KeyDelete,
     KeySpace,
+    KeyHome,
+    KeyEnd,
+    KeyInsert,
+    KeyEnter,
+    KeyTab,
+    KeyBackTab,
 }
 
 impl KeyStroke {
-    pub fn from_description(description: &str) -> Option<Self> {
-        if description.len() == 1 {
-            return Some(KeyStroke::Char(description.chars().nth(0).unwrap()));
+    /// Parse a key sequence description like `"x"`, `"gg"` or `"<space>w"`.
+    ///
+    /// The named keys are written between angle brackets, any other
+    /// character is taken as is. A `<` which doesn't start a known named key
+    /// is read as the `<` character.
+    pub fn from_description(description: &str) -> Option<Vec<Self>> {
+        let mut keys = Vec::new();
+        let mut rest = description;
+
+        while let Some(c) = rest.chars().next() {
+            if c == '<' {
+                if let Some(end) = rest.find('>') {
+                    if let Some(key) = KeyStroke::from_name(&rest[..=end]) {
+                        keys.push(key);
+                        rest = &rest[end + 1..];
+                        continue;
+                    }
+                }
+            }
+
+            keys.push(KeyStroke::Char(c));
+            rest = &rest[c.len_utf8()..];
+        }
+
+        if keys.is_empty() {
+            None
+        } else {
+            Some(keys)
         }
+    }
 
-        match description {
-            "<f1>" => Some(KeyStroke::KeyF(1)),
+    fn from_name(name: &str) -> Option<Self> {
+        if let Some(c) = parse_modified_char(name, "<c-") {
+            return Some(KeyStroke::Ctrl(c));
+        }
+
+        if let Some(c) = parse_modified_char(name, "<a-") {
+            return Some(KeyStroke::Alt(c));
+        }
+
+        if let Some(n) = parse_function_key(name) {
+            return Some(KeyStroke::KeyF(n));
+        }
+
+        match name {
             "<key_up>" => Some(KeyStroke::KeyUp),
             "<key_down>" => Some(KeyStroke::KeyDown),
             "<key_left>" => Some(KeyStroke::KeyLeft),
@@ -41,7 +97,110 @@ impl KeyStroke {
             "<del>" => Some(KeyStroke::KeyDelete),
             "<space>" => Some(KeyStroke::KeySpace),
             "<esc>" => Some(KeyStroke::KeyEscape),
+            "<home>" => Some(KeyStroke::KeyHome),
+            "<end>" => Some(KeyStroke::KeyEnd),
+            "<insert>" => Some(KeyStroke::KeyInsert),
+            "<enter>" => Some(KeyStroke::KeyEnter),
+            "<tab>" => Some(KeyStroke::KeyTab),
+            "<s-tab>" => Some(KeyStroke::KeyBackTab),
             _ => None,
         }
     }
+
+    /// Give the description of the key, the inverse of `from_description`.
+    pub fn to_description(self) -> String {
+        match self {
+            KeyStroke::Char(c) => c.to_string(),
+            KeyStroke::KeyF(n) => format!("<f{}>", n),
+            KeyStroke::Alt(c) => format!("<a-{}>", c),
+            KeyStroke::Ctrl(c) => format!("<c-{}>", c),
+            KeyStroke::KeyUp => String::from("<key_up>"),
+            KeyStroke::KeyDown => String::from("<key_down>"),
+            KeyStroke::KeyLeft => String::from("<key_left>"),
+            KeyStroke::KeyRight => String::from("<key_right>"),
+            KeyStroke::KeyPreviousPage => String::from("<page_up>"),
+            KeyStroke::KeyNextPage => String::from("<page_down>"),
+            KeyStroke::KeyEscape => String::from("<esc>"),
+            KeyStroke::KeyBackSpace => String::from("<backspace>"),
+            KeyStroke::KeyDelete => String::from("<del>"),
+            KeyStroke::KeySpace => String::from("<space>"),
+            KeyStroke::KeyHome => String::from("<home>"),
+            KeyStroke::KeyEnd => String::from("<end>"),
+            KeyStroke::KeyInsert => String::from("<insert>"),
+            KeyStroke::KeyEnter => String::from("<enter>"),
+            KeyStroke::KeyTab => String::from("<tab>"),
+            KeyStroke::KeyBackTab => String::from("<s-tab>"),
+        }
+    }
+}
+
+/// Parse a `<prefix-x>` description where `x` is a single character.
+fn parse_modified_char(name: &str, prefix: &str) -> Option<char> {
+    if !name.starts_with(prefix) || !name.ends_with('>') {
+        return None;
+    }
+
+    let mut chars = name[prefix.len()..name.len() - 1].chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+/// Parse a function key description from `<f1>` to `<f12>`.
+fn parse_function_key(name: &str) -> Option<u8> {
+    if !name.starts_with("<f") || !name.ends_with('>') {
+        return None;
+    }
+
+    match name[2..name.len() - 1].parse::<u8>() {
+        Ok(n) if (1..=12).contains(&n) => Some(n),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeyStroke;
+
+    #[test]
+    fn test_from_description_with_sequences() {
+        assert_eq!(
+            Some(vec![KeyStroke::Char('g'), KeyStroke::Char('g')]),
+            KeyStroke::from_description("gg")
+        );
+
+        assert_eq!(
+            Some(vec![KeyStroke::KeySpace, KeyStroke::Char('w')]),
+            KeyStroke::from_description("<space>w")
+        );
+
+        assert_eq!(
+            Some(vec![KeyStroke::Char('<'), KeyStroke::Char('<')]),
+            KeyStroke::from_description("<<")
+        );
+
+        assert_eq!(
+            Some(vec![KeyStroke::Ctrl('r')]),
+            KeyStroke::from_description("<c-r>")
+        );
+
+        assert_eq!(None, KeyStroke::from_description(""));
+    }
+
+    #[test]
+    fn test_from_description_with_named_keys() {
+        assert_eq!(
+            Some(vec![KeyStroke::Alt('x'), KeyStroke::KeyF(12)]),
+            KeyStroke::from_description("<a-x><f12>")
+        );
+
+        assert_eq!(
+            Some(vec![KeyStroke::KeyHome, KeyStroke::KeyEnd]),
+            KeyStroke::from_description("<home><end>")
+        );
+
+        // Out of range function keys are not named keys.
+        assert_eq!(5, KeyStroke::from_description("<f13>").unwrap().len());
+    }
 }
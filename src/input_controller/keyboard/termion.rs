@@ -1,46 +1,80 @@
 use std::io::Read;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::Duration;
 
 use super::{KeyStroke, Keyboard};
 
 use termion::event::Key;
-use termion::input::{Keys, TermRead};
+use termion::input::TermRead;
 
-pub struct TermionKeyboard<R: Read> {
-    key_reader: Keys<R>,
+/// Read the keystrokes from a dedicated thread.
+///
+/// The reader thread is required in order to wait for a key with a timeout,
+/// which is not possible with a blocking `Read`.
+pub struct TermionKeyboard {
+    receiver: Receiver<KeyStroke>,
 }
 
-impl<R: Read> TermionKeyboard<R> {
-    pub fn from_reader(reader: R) -> Self {
-        Self {
-            key_reader: reader.keys(),
-        }
+impl TermionKeyboard {
+    pub fn from_reader<R: Read + Send + 'static>(reader: R) -> Self {
+        let (sender, receiver) = channel();
+
+        thread::spawn(move || {
+            for res in reader.keys() {
+                let key = match res {
+                    Ok(key) => key,
+                    Err(err) => {
+                        error!("failed to read the keyboard input: {}", err);
+                        break;
+                    }
+                };
+
+                if let Some(keystroke) = to_keystroke(key) {
+                    if sender.send(keystroke).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self { receiver }
     }
 }
 
-impl<R: Read> Keyboard for TermionKeyboard<R> {
+impl Keyboard for TermionKeyboard {
     fn get_next_keystroke(&mut self) -> Option<KeyStroke> {
-        let res = self.key_reader.next()?;
-
-        match res.unwrap() {
-            Key::Backspace => Some(KeyStroke::KeyBackSpace),
-            Key::Left => Some(KeyStroke::KeyLeft),
-            Key::Right => Some(KeyStroke::KeyRight),
-            Key::Up => Some(KeyStroke::KeyUp),
-            Key::Down => Some(KeyStroke::KeyDown),
-            Key::Home => None,
-            Key::End => None,
-            Key::PageUp => Some(KeyStroke::KeyPreviousPage),
-            Key::PageDown => Some(KeyStroke::KeyNextPage),
-            Key::Delete => Some(KeyStroke::KeyDelete),
-            Key::Insert => None,
-            Key::F(n) => Some(KeyStroke::KeyF(n)),
-            Key::Alt(c) => Some(KeyStroke::Alt(c)),
-            Key::Ctrl(_) => None,
-            Key::Null => None,
-            Key::Esc => Some(KeyStroke::KeyEscape),
-            Key::Char(' ') => Some(KeyStroke::KeySpace),
-            Key::Char(c) => Some(KeyStroke::Char(c)),
-            _ => None,
-        }
+        self.receiver.recv().ok()
+    }
+
+    fn get_next_keystroke_before(&mut self, timeout: Duration) -> Option<KeyStroke> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+}
+
+fn to_keystroke(key: Key) -> Option<KeyStroke> {
+    match key {
+        Key::Backspace => Some(KeyStroke::KeyBackSpace),
+        Key::Left => Some(KeyStroke::KeyLeft),
+        Key::Right => Some(KeyStroke::KeyRight),
+        Key::Up => Some(KeyStroke::KeyUp),
+        Key::Down => Some(KeyStroke::KeyDown),
+        Key::Home => Some(KeyStroke::KeyHome),
+        Key::End => Some(KeyStroke::KeyEnd),
+        Key::PageUp => Some(KeyStroke::KeyPreviousPage),
+        Key::PageDown => Some(KeyStroke::KeyNextPage),
+        Key::Delete => Some(KeyStroke::KeyDelete),
+        Key::Insert => Some(KeyStroke::KeyInsert),
+        Key::F(n) => Some(KeyStroke::KeyF(n)),
+        Key::Alt(c) => Some(KeyStroke::Alt(c)),
+        Key::Ctrl(c) => Some(KeyStroke::Ctrl(c)),
+        Key::Null => None,
+        Key::Esc => Some(KeyStroke::KeyEscape),
+        Key::BackTab => Some(KeyStroke::KeyBackTab),
+        Key::Char(' ') => Some(KeyStroke::KeySpace),
+        Key::Char('\n') => Some(KeyStroke::KeyEnter),
+        Key::Char('\t') => Some(KeyStroke::KeyTab),
+        Key::Char(c) => Some(KeyStroke::Char(c)),
+        _ => None,
     }
 }
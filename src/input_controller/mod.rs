@@ -1,14 +1,22 @@
 mod actions;
+mod command;
+mod command_line;
 pub mod keyboard;
 mod mode_actions;
 
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use self::actions::{Action, Response};
+use self::actions::{rpc, Action, Operator, Response, SearchDirection, WindowCommand};
+use self::command::Command;
+use self::command_line::{CommandLine, CommandLineEvent};
 use self::keyboard::{KeyStroke, Keyboard};
-use self::mode_actions::ModeActions;
-use crate::core::ClientToClientWriter;
+use self::mode_actions::{Binding, ModeActions};
+use crate::core::{ClientMessage, ClientToClientWriter};
+use crate::split::{SplitDirection, SplitTree};
+use crate::state::SharedState;
 
 use failure::Error;
 use xi_rpc::Peer;
@@ -17,6 +25,26 @@ lazy_static! {
     static ref PASTE_BUFFER: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
 }
 
+/// How long an uncompleted key sequence waits for its next key before being
+/// resolved with the keys typed so far.
+const SEQUENCE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// The maximum number of times an action can be repeated with a count
+/// prefix.
+const MAX_COUNT: u32 = 9999;
+
+/// The `:set` options handled by the client instead of the core.
+const CLIENT_OPTIONS: [&str; 5] = [
+    "wrap",
+    "nowrap",
+    "wrap_column",
+    "line_numbers",
+    "sign_column",
+];
+
+/// The percent of a split given or taken to a pane by each resize.
+const RESIZE_STEP: i16 = 5;
+
 #[derive(Debug, Default, Deserialize)]
 pub struct Config {
     #[serde(default)]
@@ -27,6 +55,8 @@ pub struct Config {
     visual_mode: HashMap<String, String>,
     #[serde(default)]
     action_mode: HashMap<String, String>,
+    #[serde(default)]
+    operator_pending_mode: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -35,6 +65,9 @@ pub enum Mode {
     Insert,
     Visual,
     Action,
+    OperatorPending,
+    Command,
+    Search,
 }
 
 impl Mode {
@@ -45,19 +78,91 @@ impl Mode {
             Mode::Insert => String::from("INSERT"),
             Mode::Visual => String::from("VISUAL"),
             Mode::Action => String::from("ACTION"),
+            Mode::OperatorPending => String::from("OPERATOR"),
+            Mode::Command => String::from("COMMAND"),
+            Mode::Search => String::from("SEARCH"),
+        }
+    }
+}
+
+/// A file opened inside an xi view.
+#[derive(Debug, Clone)]
+struct Buffer {
+    view_id: String,
+    path: String,
+}
+
+/// A tab page, with its own panes.
+#[derive(Debug, Clone)]
+struct TabPage {
+    panes: SplitTree,
+    /// The view of the focused pane.
+    view_id: String,
+}
+
+impl TabPage {
+    /// Display `new_view_id` in place of `view_id`, or close the pane of
+    /// `view_id` if `new_view_id` is already displayed.
+    fn replace_view(&mut self, view_id: &str, new_view_id: &str) {
+        if !self.panes.contains(view_id) {
+            return;
+        }
+
+        let focused_view = if self.panes.contains(new_view_id) {
+            self.panes.close(view_id)
+        } else {
+            self.panes.replace(view_id, new_view_id);
+            Some(new_view_id.to_owned())
+        };
+
+        if self.view_id == view_id {
+            if let Some(focused_view) = focused_view {
+                self.view_id = focused_view;
+            }
         }
     }
 }
 
 pub struct InputController {
     keyboard: Box<dyn Keyboard>,
+    /// The view of the active buffer.
     view_id: String,
+    /// The opened buffers, in their opening order.
+    buffers: Vec<Buffer>,
+    /// The views created by the splits, with the view of the buffer they
+    /// display. They are closed once no pane displays them anymore.
+    split_views: HashMap<String, String>,
+    /// The panes of the current tab page with the views they display.
+    panes: SplitTree,
+    /// The tab pages. The current one is described by `panes` and `view_id`,
+    /// its entry is only updated when leaving it.
+    tabs: Vec<TabPage>,
+    current_tab: usize,
     normal_mode: ModeActions,
     insert_mode: ModeActions,
     visual_mode: ModeActions,
     action_mode: ModeActions,
+    operator_pending_mode: ModeActions,
+    /// The (empty) keymap of the modes typing into a prompt.
+    prompt_mode: ModeActions,
     mode: Mode,
     front_event_writer: ClientToClientWriter,
+    /// The number of times the next action should be repeated.
+    ///
+    /// It is built by typing digits before an action in normal and visual
+    /// modes, like `5j`.
+    count: Option<u32>,
+    /// The operator waiting for a motion with the count typed before it.
+    operator: Option<(Operator, u32)>,
+    /// The pending keys currently displayed inside the status bar.
+    displayed_pending_keys: String,
+    command_line: CommandLine,
+    search_line: CommandLine,
+    /// The direction of the last search, followed by `n`.
+    search_direction: SearchDirection,
+    /// The pattern of the last validated search.
+    last_search: Option<String>,
+    state: SharedState,
 }
 
 impl InputController {
@@ -65,104 +170,1080 @@ impl InputController {
         keyboard: Box<dyn Keyboard>,
         client_to_client_writer: ClientToClientWriter,
         config: &Config,
+        state: SharedState,
     ) -> Self {
         Self {
             keyboard,
             view_id: String::new(),
+            buffers: Vec::new(),
+            split_views: HashMap::new(),
+            panes: SplitTree::new(""),
+            tabs: vec![TabPage {
+                panes: SplitTree::new(""),
+                view_id: String::new(),
+            }],
+            current_tab: 0,
             normal_mode: ModeActions::setup(Mode::Normal, &config.normal_mode),
             insert_mode: ModeActions::setup(Mode::Insert, &config.insert_mode),
             visual_mode: ModeActions::setup(Mode::Visual, &config.visual_mode),
             action_mode: ModeActions::setup(Mode::Action, &config.action_mode),
+            operator_pending_mode: ModeActions::setup(
+                Mode::OperatorPending,
+                &config.operator_pending_mode,
+            ),
+            prompt_mode: ModeActions::setup(Mode::Command, &HashMap::new()),
             mode: Mode::Normal,
             front_event_writer: client_to_client_writer,
+            count: None,
+            operator: None,
+            displayed_pending_keys: String::new(),
+            command_line: CommandLine::default(),
+            search_line: CommandLine::default(),
+            search_direction: SearchDirection::Forward,
+            last_search: None,
+            state,
         }
     }
 
     pub fn open_file(&mut self, core: &dyn Peer, file_path: &str) -> Result<(), Error> {
+        self.open_view(core, file_path)?;
+
+        core.send_rpc_notification("set_theme", &json!({"theme_name": "Solarized (light)" }));
+        self.front_event_writer.send(ClientMessage::AddStatusItem {
+            key: String::from("change-mode"),
+            value: self.mode.to_string(),
+            alignment: String::from("left"),
+        });
+        self.front_event_writer.send(ClientMessage::AddStatusItem {
+            key: String::from("pending-keys"),
+            value: String::new(),
+            alignment: String::from("right"),
+        });
+
+        Ok(())
+    }
+
+    /// Close the views of all the buffers.
+    ///
+    /// It must be called once the EventController is stopped, after it sent
+    /// the pending writes to the core.
+    pub fn shutdown(&self, core: &dyn Peer) {
+        let views = self
+            .buffers
+            .iter()
+            .map(|buffer| &buffer.view_id)
+            .chain(self.split_views.keys());
+        for view_id in views {
+            core.send_rpc_notification("close_view", &json!({ "view_id": view_id }));
+        }
+
+        core.send_rpc_notification("exit", &json!({}));
+    }
+
+    /// Ask the core for a new view on the given file and add it to the
+    /// buffers.
+    fn create_view(&mut self, core: &dyn Peer, file_path: &str) -> Result<String, Error> {
+        let view_id = self.new_view(core, file_path)?;
+
+        self.buffers.push(Buffer {
+            view_id: view_id.clone(),
+            path: file_path.to_owned(),
+        });
+
+        Ok(view_id)
+    }
+
+    /// Ask the core for a new view on the given file.
+    ///
+    /// The client creates the view on the "set_path_for_view" notification.
+    fn new_view(&mut self, core: &dyn Peer, file_path: &str) -> Result<String, Error> {
         let view_id = core
             .send_rpc_request("new_view", &json!({ "file_path": file_path }))
-            .expect("failed to create the new view");
+            .map_err(|err| format_err!("failed to create the new view: {:?}", err))?;
 
-        self.view_id = view_id.as_str().unwrap().to_string();
+        let view_id = view_id
+            .as_str()
+            .ok_or_else(|| format_err!("invalid view id: {}", view_id))?
+            .to_string();
 
-        self.front_event_writer.send_rpc_notification(
-            "set_path_for_view",
-            &json!({
-                "view_id": self.view_id,
-                "path": file_path,
-            }),
-        );
+        self.front_event_writer.send(ClientMessage::SetPathForView {
+            view_id: view_id.clone(),
+            path: file_path.to_owned(),
+        });
 
-        core.send_rpc_notification("set_theme", &json!({"theme_name": "Solarized (light)" }));
-        self.front_event_writer.send_rpc_notification(
-            "add_status_item",
-            &json!({
-                "key": "change-mode",
-                "value": self.mode.to_string(),
-                "alignment": "left",
-            }),
+        Ok(view_id)
+    }
+
+    /// Open the file in a new buffer displayed by the current pane.
+    fn open_view(&mut self, core: &dyn Peer, file_path: &str) -> Result<(), Error> {
+        let view_id = self.create_view(core, file_path)?;
+
+        self.panes.replace(&self.view_id, &view_id);
+        self.view_id = view_id;
+        self.send_layout(core);
+
+        Ok(())
+    }
+
+    /// Make the buffer at `idx` the current one.
+    ///
+    /// A buffer already displayed by an another pane is focused, the others
+    /// are displayed by the current pane.
+    fn switch_buffer(&mut self, idx: usize, core: &dyn Peer) {
+        let view_id = self.buffers[idx].view_id.clone();
+        if view_id == self.buffer_view(&self.view_id) {
+            return;
+        }
+
+        if !self.panes.contains(&view_id) {
+            self.panes.replace(&self.view_id, &view_id);
+        }
+
+        self.view_id = view_id;
+        self.send_layout(core);
+    }
+
+    /// Give the tab pages, the panes of the current one and the focused view
+    /// to the client.
+    ///
+    /// The split views no longer displayed by any pane are closed.
+    fn send_layout(&mut self, core: &dyn Peer) {
+        let tabs: Vec<String> = (0..self.tabs.len())
+            .map(|idx| self.tab_label(idx))
+            .collect();
+
+        self.front_event_writer.send(ClientMessage::SetLayout {
+            tabs,
+            current_tab: self.current_tab,
+            panes: self.panes.clone(),
+            focused_view: self.view_id.clone(),
+        });
+
+        self.close_hidden_split_views(core);
+    }
+
+    fn close_hidden_split_views(&mut self, core: &dyn Peer) {
+        let is_displayed = |view_id: &str| {
+            self.panes.contains(view_id)
+                || self
+                    .tabs
+                    .iter()
+                    .enumerate()
+                    .any(|(idx, tab)| idx != self.current_tab && tab.panes.contains(view_id))
+        };
+
+        let hidden_views: Vec<String> = self
+            .split_views
+            .keys()
+            .filter(|view_id| !is_displayed(view_id))
+            .cloned()
+            .collect();
+
+        for view_id in hidden_views {
+            self.split_views.remove(&view_id);
+            core.send_rpc_notification("close_view", &json!({ "view_id": view_id }));
+            self.front_event_writer
+                .send(ClientMessage::RemoveView { view_id });
+        }
+    }
+
+    /// Give the view of the buffer displayed by a view, which is itself
+    /// unless it is a split view.
+    fn buffer_view<'a>(&'a self, view_id: &'a str) -> &'a str {
+        self.split_views
+            .get(view_id)
+            .map(String::as_str)
+            .unwrap_or(view_id)
+    }
+
+    /// Give the name of the file focused by a tab page.
+    fn tab_label(&self, idx: usize) -> String {
+        let view_id = if idx == self.current_tab {
+            self.buffer_view(&self.view_id)
+        } else {
+            self.buffer_view(&self.tabs[idx].view_id)
+        };
+
+        self.buffers
+            .iter()
+            .find(|buffer| buffer.view_id == view_id)
+            .map(|buffer| match Path::new(&buffer.path).file_name() {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None => buffer.path.clone(),
+            })
+            .unwrap_or_default()
+    }
+
+    /// Store the panes of the current tab page inside its entry.
+    fn save_current_tab(&mut self) {
+        self.tabs[self.current_tab] = TabPage {
+            panes: self.panes.clone(),
+            view_id: self.view_id.clone(),
+        };
+    }
+
+    /// Make the tab page at `idx` the current one, the current tab page must
+    /// have been saved.
+    fn load_tab(&mut self, idx: usize) {
+        self.current_tab = idx;
+        self.panes = self.tabs[idx].panes.clone();
+        self.view_id = self.tabs[idx].view_id.clone();
+    }
+
+    /// Open a new tab page after the current one, on `file_path` or on the
+    /// current buffer.
+    fn new_tab(&mut self, file_path: Option<&str>, core: &dyn Peer) -> Result<(), Error> {
+        let view_id = match file_path {
+            None => self.buffer_view(&self.view_id).to_owned(),
+            Some(path) => match self.buffers.iter().find(|buffer| buffer.path == path) {
+                Some(buffer) => buffer.view_id.clone(),
+                None => self.create_view(core, path)?,
+            },
+        };
+
+        self.save_current_tab();
+        self.tabs.insert(
+            self.current_tab + 1,
+            TabPage {
+                panes: SplitTree::new(&view_id),
+                view_id,
+            },
         );
+        self.load_tab(self.current_tab + 1);
+        self.send_layout(core);
+
+        Ok(())
+    }
+
+    /// Close the current tab page, its buffers stay opened in the background.
+    fn close_tab(&mut self, core: &dyn Peer) -> Result<(), Error> {
+        if self.tabs.len() == 1 {
+            return Err(format_err!("Cannot close last tab page"));
+        }
+
+        self.tabs.remove(self.current_tab);
+        self.load_tab(self.current_tab.min(self.tabs.len() - 1));
+        self.send_layout(core);
+
+        Ok(())
+    }
+
+    /// Go `count` tab pages forward, or backward if `reverse` is set.
+    ///
+    /// It wraps around the first and last tab pages.
+    fn switch_tab(&mut self, reverse: bool, count: u32, core: &dyn Peer) {
+        let len = self.tabs.len();
+        let shift = count as usize % len;
+        let idx = if reverse {
+            (self.current_tab + len - shift) % len
+        } else {
+            (self.current_tab + shift) % len
+        };
+
+        self.go_to_tab(idx, core);
+    }
+
+    /// Go to the tab page at the given index, if it exists.
+    fn go_to_tab(&mut self, idx: usize, core: &dyn Peer) {
+        if idx < self.tabs.len() && idx != self.current_tab {
+            self.save_current_tab();
+            self.load_tab(idx);
+            self.send_layout(core);
+        }
+    }
+
+    /// Split the current pane. The new pane displays `file_path` or the
+    /// current file and takes the focus.
+    fn split_window(
+        &mut self,
+        direction: SplitDirection,
+        file_path: Option<&str>,
+        core: &dyn Peer,
+    ) -> Result<(), Error> {
+        let idx = match file_path {
+            None => Some(self.current_buffer_idx()),
+            Some(path) => self.buffers.iter().position(|buffer| buffer.path == path),
+        };
+
+        let view_id = match idx {
+            Some(idx) => {
+                // Each pane needs its own view, the core keeps the views of
+                // a same file in sync. The buffer list keeps only one of
+                // them.
+                let buffer = self.buffers[idx].clone();
+                let view_id = self.new_view(core, &buffer.path)?;
+                self.split_views.insert(view_id.clone(), buffer.view_id);
+                view_id
+            }
+            // A file not opened yet gets its own buffer.
+            None => self.create_view(core, file_path.unwrap_or_default())?,
+        };
+
+        self.panes.split(&self.view_id, &view_id, direction);
+        self.view_id = view_id;
+        self.send_layout(core);
+
+        Ok(())
+    }
+
+    /// Close the current pane, its buffer stays opened in the background.
+    ///
+    /// The view of a split is closed with its pane.
+    ///
+    /// Closing the last pane of a tab page closes the tab page.
+    fn close_window(&mut self, core: &dyn Peer) -> Result<(), Error> {
+        if self.panes.pane_count() == 1 {
+            return self
+                .close_tab(core)
+                .map_err(|_| format_err!("Cannot close last window"));
+        }
+
+        let view_id = self
+            .panes
+            .close(&self.view_id)
+            .ok_or_else(|| format_err!("Cannot close last window"))?;
+
+        self.view_id = view_id;
+        self.send_layout(core);
+
+        Ok(())
+    }
+
+    fn execute_window_command(
+        &mut self,
+        command: WindowCommand,
+        count: u32,
+        core: &dyn Peer,
+    ) -> Result<(), Error> {
+        match command {
+            WindowCommand::Split(direction) => self.split_window(direction, None, core)?,
+            WindowCommand::Focus(direction) => {
+                for _ in 0..count {
+                    match self.panes.neighbour(&self.view_id, direction) {
+                        Some(view_id) => self.view_id = view_id,
+                        None => break,
+                    }
+                }
+
+                self.send_layout(core);
+            }
+            WindowCommand::Resize(axis, grow) => {
+                let step = if grow { RESIZE_STEP } else { -RESIZE_STEP };
+                // Over 20 steps the pane already has its maximum size.
+                let delta = step * count.min(20) as i16;
+
+                if self.panes.resize(&self.view_id, axis, delta) {
+                    self.send_layout(core);
+                }
+            }
+            WindowCommand::Close => self.close_window(core)?,
+        }
+
+        Ok(())
+    }
+
+    fn current_buffer_idx(&self) -> usize {
+        let view_id = self.buffer_view(&self.view_id);
+        self.buffers
+            .iter()
+            .position(|buffer| buffer.view_id == view_id)
+            .unwrap_or(0)
+    }
+
+    /// Close the current buffer and switch to the next one.
+    fn delete_buffer(&mut self, force: bool, core: &dyn Peer) -> Result<(), Error> {
+        if self.buffers.len() == 1 {
+            return Err(format_err!("Cannot delete the last buffer"));
+        }
+
+        if !force {
+            self.ensure_view_saved()?;
+        }
+
+        let idx = self.current_buffer_idx();
+        let buffer = self.buffers.remove(idx);
+        let next_view_id = self.buffers[idx.min(self.buffers.len() - 1)]
+            .view_id
+            .clone();
+
+        // The next buffer takes the place of the deleted one and of its split
+        // views in all the tab pages.
+        let mut views: Vec<String> = self
+            .split_views
+            .iter()
+            .filter(|(_, buffer_view)| **buffer_view == buffer.view_id)
+            .map(|(view_id, _)| view_id.clone())
+            .collect();
+        views.push(buffer.view_id.clone());
+
+        self.save_current_tab();
+        for tab in self.tabs.iter_mut() {
+            for view_id in &views {
+                tab.replace_view(view_id, &next_view_id);
+            }
+        }
+        self.load_tab(self.current_tab);
+        self.send_layout(core);
+
+        core.send_rpc_notification("close_view", &json!({ "view_id": buffer.view_id }));
+        self.front_event_writer.send(ClientMessage::RemoveView {
+            view_id: buffer.view_id,
+        });
 
         Ok(())
     }
 
+    /// Describe the opened buffers on a single line, like
+    /// `1 %  "main.rs"  2 +  "README.md"`.
+    ///
+    /// The current buffer is marked with `%` and the modified ones with `+`.
+    fn list_buffers(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let current_view = self.buffer_view(&self.view_id);
+
+        self.buffers
+            .iter()
+            .enumerate()
+            .map(|(idx, buffer)| {
+                format!(
+                    "{} {}{} \"{}\"",
+                    idx + 1,
+                    if buffer.view_id == current_view {
+                        '%'
+                    } else {
+                        ' '
+                    },
+                    if state.unsaved_views.contains(&buffer.view_id) {
+                        '+'
+                    } else {
+                        ' '
+                    },
+                    buffer.path,
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("  ")
+    }
+
     pub fn start_keyboard_event_loop(&mut self, core: &dyn Peer) -> Result<(), Error> {
-        loop {
-            let key_res = self.keyboard.get_next_keystroke();
-
-            if let Some(key) = key_res {
-                let mut action = match self.mode {
-                    Mode::Normal => self.normal_mode.get_action_from_keystroke(key),
-                    Mode::Insert => self.insert_mode.get_action_from_keystroke(key),
-                    Mode::Visual => self.visual_mode.get_action_from_keystroke(key),
-                    Mode::Action => self.action_mode.get_action_from_keystroke(key),
+        'event_loop: loop {
+            if self.mode == Mode::Command || self.mode == Mode::Search {
+                let key = match self.keyboard.get_next_keystroke() {
+                    Some(key) => key,
+                    None => break,
+                };
+
+                let res = if self.mode == Mode::Command {
+                    self.handle_command_line_keystroke(key, core)
+                } else {
+                    self.handle_search_keystroke(key, core)
                 };
 
-                if action.is_none() && self.mode == Mode::Insert {
-                    action = Some(Action::InsertKeyStroke(key));
-                } else if action.is_none() {
-                    continue;
+                match res {
+                    Ok(res) => {
+                        if !self.handle_response(res, None, core) {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        self.handle_response(Response::SwitchToNormalMode, None, core);
+
+                        // Sent after the mode change in order to not be
+                        // erased by it.
+                        self.alert(&err.to_string());
+                    }
                 }
 
-                let res =
-                    action
-                        .unwrap()
-                        .execute(&self.view_id, core, &mut self.front_event_writer);
+                continue;
+            }
 
-                match res {
-                    Response::Continue => continue,
-                    Response::Stop => break,
-                    Response::SwitchToInsertMode => self.mode = Mode::Insert,
-                    Response::SwitchToNormalMode => self.mode = Mode::Normal,
-                    Response::SwitchToVisualMode => self.mode = Mode::Visual,
-                    Response::SwitchToActionMode => self.mode = Mode::Action,
+            let bindings = if self.mode_actions().is_pending() {
+                match self.keyboard.get_next_keystroke_before(SEQUENCE_TIMEOUT) {
+                    Some(key) => self.handle_keystroke(key),
+                    // The user stopped in the middle of a sequence, use the
+                    // keys typed so far.
+                    None => self.mode_actions().flush_pending(),
                 }
+            } else {
+                match self.keyboard.get_next_keystroke() {
+                    Some(key) => self.handle_keystroke(key),
+                    None => break,
+                }
+            };
 
-                core.send_rpc_notification(
-                    "edit",
-                    &json!({ "method": "collapse_selections", "view_id": self.view_id}),
-                );
+            for binding in bindings {
+                let action = match self.binding_action(binding) {
+                    Some(action) => action,
+                    None => continue,
+                };
 
-                self.front_event_writer.send_rpc_notification(
-                    "update_status_item",
-                    &json!({
-                        "key": "change-mode",
-                        "value": self.mode.to_string(),
-                    }),
-                );
+                let typed_count = self.count.take();
+                let count = typed_count.unwrap_or(1);
+                let res = match self.operator.take() {
+                    // The escape key cancels the operator.
+                    Some(_) if action == Action::SwitchToNormalMode => Response::SwitchToNormalMode,
+                    Some((operator, operator_count)) => operator.execute(
+                        action,
+                        (operator_count * count).min(MAX_COUNT),
+                        &self.view_id,
+                        core,
+                        &mut self.front_event_writer,
+                    ),
+                    None => {
+                        let mut res = Response::Continue;
+                        for _ in 0..count {
+                            res = action.execute(&self.view_id, core, &mut self.front_event_writer);
+                            if res != Response::Continue {
+                                break;
+                            }
+                        }
+                        res
+                    }
+                };
+
+                if !self.handle_response(res, typed_count, core) {
+                    break 'event_loop;
+                }
             }
+
+            self.update_pending_keys();
         }
 
+        self.front_event_writer.send(ClientMessage::Shutdown);
+
+        Ok(())
+    }
+
+    /// Apply the response of an executed action, with the count typed before
+    /// it.
+    ///
+    /// It returns false if the event loop must stop.
+    fn handle_response(&mut self, res: Response, count: Option<u32>, core: &dyn Peer) -> bool {
+        match res {
+            Response::Continue => return true,
+            Response::Stop => return false,
+            Response::SwitchToInsertMode => self.switch_mode(Mode::Insert),
+            Response::SwitchToNormalMode => self.switch_mode(Mode::Normal),
+            Response::SwitchToVisualMode => self.switch_mode(Mode::Visual),
+            Response::SwitchToActionMode => self.switch_mode(Mode::Action),
+            Response::SwitchToOperatorPendingMode(operator) => {
+                self.switch_mode(Mode::OperatorPending);
+                self.operator = Some((operator, count.unwrap_or(1)));
+            }
+            Response::SwitchToCommandMode => {
+                self.switch_mode(Mode::Command);
+                self.command_line.start();
+                self.show_command_line();
+            }
+            Response::SwitchToSearchMode(direction) => {
+                self.switch_mode(Mode::Search);
+                self.search_direction = direction;
+                self.search_line.start();
+                self.show_command_line();
+            }
+            Response::Window(command) => {
+                if let Err(err) = self.execute_window_command(command, count.unwrap_or(1), core) {
+                    self.alert(&err.to_string());
+                }
+
+                return true;
+            }
+            Response::SwitchTab { reverse } => {
+                match count {
+                    // Like vim, `{count}gt` goes to the tab page number
+                    // `count` while `{count}gT` goes `count` tab pages back.
+                    Some(number) if !reverse => self.go_to_tab(number as usize - 1, core),
+                    _ => self.switch_tab(reverse, count.unwrap_or(1), core),
+                }
+                return true;
+            }
+            Response::RepeatSearch { reverse } => {
+                if let Err(err) = self.repeat_search(reverse, count.unwrap_or(1), core) {
+                    self.alert(&err.to_string());
+                }
+
+                // The cursor is already on the match, there is no selection
+                // to collapse.
+                return true;
+            }
+        }
+
+        core.send_rpc_notification(
+            "edit",
+            &json!({ "method": "collapse_selections", "view_id": self.view_id}),
+        );
+
         self.front_event_writer
-            .send_rpc_notification("command", &json!({"method": "exit"}));
+            .send(ClientMessage::UpdateStatusItem {
+                key: String::from("change-mode"),
+                value: self.mode.to_string(),
+            });
+
+        true
+    }
+
+    fn handle_command_line_keystroke(
+        &mut self,
+        key: KeyStroke,
+        core: &dyn Peer,
+    ) -> Result<Response, Error> {
+        match self.command_line.handle_keystroke(key) {
+            CommandLineEvent::Changed => {
+                self.show_command_line();
+                Ok(Response::Continue)
+            }
+            CommandLineEvent::Canceled => Ok(Response::SwitchToNormalMode),
+            CommandLineEvent::Validated(ref input) if input.trim().is_empty() => {
+                Ok(Response::SwitchToNormalMode)
+            }
+            CommandLineEvent::Validated(input) => {
+                let command = Command::parse(&input)?;
+                self.execute_command(command, core)
+            }
+        }
+    }
+
+    fn handle_search_keystroke(
+        &mut self,
+        key: KeyStroke,
+        core: &dyn Peer,
+    ) -> Result<Response, Error> {
+        match self.search_line.handle_keystroke(key) {
+            CommandLineEvent::Changed => {
+                // Highlight the matches while the pattern is typed.
+                let pattern = self.search_line.content();
+                if pattern.is_empty() {
+                    rpc::find(&self.view_id, self.last_search.as_deref(), core);
+                } else {
+                    rpc::find(&self.view_id, Some(&pattern), core);
+                }
+
+                self.show_command_line();
+                Ok(Response::Continue)
+            }
+            CommandLineEvent::Canceled => {
+                // Give back the highlights of the previous search.
+                rpc::find(&self.view_id, self.last_search.as_deref(), core);
+                Ok(Response::SwitchToNormalMode)
+            }
+            CommandLineEvent::Validated(pattern) => {
+                if !pattern.is_empty() {
+                    self.last_search = Some(pattern);
+                }
+
+                self.repeat_search(false, 1, core)?;
+                Ok(Response::SwitchToNormalMode)
+            }
+        }
+    }
+
+    /// Jump `count` times to the next match of the last search.
+    ///
+    /// The jump follows the direction of the last search, unless `reverse`
+    /// is set.
+    fn repeat_search(&mut self, reverse: bool, count: u32, core: &dyn Peer) -> Result<(), Error> {
+        let pattern = self
+            .last_search
+            .as_deref()
+            .ok_or_else(|| format_err!("No previous regular expression"))?;
+
+        // The search is set again in case the view has changed since.
+        rpc::find(&self.view_id, Some(pattern), core);
+
+        let direction = if reverse {
+            self.search_direction.reverse()
+        } else {
+            self.search_direction
+        };
+
+        for _ in 0..count {
+            rpc::find_next(&self.view_id, direction, core);
+        }
 
         Ok(())
     }
+
+    fn execute_command(&mut self, command: Command, core: &dyn Peer) -> Result<Response, Error> {
+        let res = match command {
+            Command::Write(file_path) => {
+                if let Some(ref file_path) = file_path {
+                    let idx = self.current_buffer_idx();
+                    self.buffers[idx].path = file_path.clone();
+                }
+
+                rpc::write_to_file(
+                    &self.view_id,
+                    file_path.as_deref(),
+                    &mut self.front_event_writer,
+                )
+            }
+            Command::Quit { force } => {
+                if self.panes.pane_count() > 1 || self.tabs.len() > 1 {
+                    self.close_window(core)?;
+                    return Ok(Response::SwitchToNormalMode);
+                }
+
+                if !force {
+                    self.ensure_all_views_saved()?;
+                }
+
+                rpc::quite()
+            }
+            Command::WriteAndQuit => {
+                rpc::write_to_file(&self.view_id, None, &mut self.front_event_writer);
+
+                if self.panes.pane_count() > 1 || self.tabs.len() > 1 {
+                    self.close_window(core)?;
+                    return Ok(Response::SwitchToNormalMode);
+                }
+
+                // The current view is written, the other ones could still
+                // have some changes.
+                self.ensure_other_views_saved()?;
+
+                rpc::quite()
+            }
+            Command::Split(file_path) => {
+                self.split_window(SplitDirection::Horizontal, file_path.as_deref(), core)?;
+                Response::SwitchToNormalMode
+            }
+            Command::VerticalSplit(file_path) => {
+                self.split_window(SplitDirection::Vertical, file_path.as_deref(), core)?;
+                Response::SwitchToNormalMode
+            }
+            Command::Close => {
+                self.close_window(core)?;
+                Response::SwitchToNormalMode
+            }
+            Command::TabNew(file_path) => {
+                self.new_tab(file_path.as_deref(), core)?;
+                Response::SwitchToNormalMode
+            }
+            Command::TabClose => {
+                self.close_tab(core)?;
+                Response::SwitchToNormalMode
+            }
+            Command::TabNext => {
+                self.switch_tab(false, 1, core);
+                Response::SwitchToNormalMode
+            }
+            Command::TabPrevious => {
+                self.switch_tab(true, 1, core);
+                Response::SwitchToNormalMode
+            }
+            Command::Edit(path) => {
+                // The current buffer stays opened in the background.
+                match self.buffers.iter().position(|buffer| buffer.path == path) {
+                    Some(idx) => self.switch_buffer(idx, core),
+                    None => self.open_view(core, &path)?,
+                }
+
+                Response::SwitchToNormalMode
+            }
+            Command::NextBuffer => {
+                let idx = (self.current_buffer_idx() + 1) % self.buffers.len();
+                self.switch_buffer(idx, core);
+                Response::SwitchToNormalMode
+            }
+            Command::PreviousBuffer => {
+                let len = self.buffers.len();
+                let idx = (self.current_buffer_idx() + len - 1) % len;
+                self.switch_buffer(idx, core);
+                Response::SwitchToNormalMode
+            }
+            Command::GotoBuffer(number) => {
+                if number == 0 || number > self.buffers.len() {
+                    return Err(format_err!("Buffer {} does not exist", number));
+                }
+
+                self.switch_buffer(number - 1, core);
+                Response::SwitchToNormalMode
+            }
+            Command::ListBuffers => {
+                // The list is sent after the mode change in order to not be
+                // erased by it.
+                self.handle_response(Response::SwitchToNormalMode, None, core);
+                let list = self.list_buffers();
+                self.alert(&list);
+                Response::Continue
+            }
+            Command::DeleteBuffer { force } => {
+                self.delete_buffer(force, core)?;
+                Response::SwitchToNormalMode
+            }
+            Command::GotoLine(line) => {
+                // The core lines start at 0.
+                rpc::goto_line(&self.view_id, line.saturating_sub(1), core);
+                Response::SwitchToNormalMode
+            }
+            Command::Set { key, value } => {
+                if CLIENT_OPTIONS.contains(&key.as_str()) {
+                    self.front_event_writer
+                        .send(ClientMessage::SetOption { key, value });
+                } else {
+                    rpc::set_config(&self.view_id, &key, &value, core);
+                }
+
+                Response::SwitchToNormalMode
+            }
+            Command::Substitute(mut substitution) => {
+                if substitution.pattern.is_empty() {
+                    // The last search is a literal string.
+                    substitution.pattern = self
+                        .last_search
+                        .clone()
+                        .ok_or_else(|| format_err!("No previous regular expression"))?;
+                    substitution.regex = false;
+                }
+
+                rpc::substitute(&self.view_id, &substitution, &mut self.front_event_writer)
+            }
+        };
+
+        Ok(res)
+    }
+
+    /// Display a message inside the status bar.
+    fn alert(&mut self, msg: &str) {
+        self.front_event_writer.send(ClientMessage::Alert {
+            msg: msg.to_owned(),
+        });
+    }
+
+    /// Check that no buffer has unsaved changes, starting with the current
+    /// one.
+    fn ensure_all_views_saved(&self) -> Result<(), Error> {
+        self.ensure_view_saved()?;
+        self.ensure_other_views_saved()
+    }
+
+    /// Check that no buffer but the current one has unsaved changes.
+    fn ensure_other_views_saved(&self) -> Result<(), Error> {
+        let state = self.state.lock().unwrap();
+        let current_view = self.buffer_view(&self.view_id);
+        match self.buffers.iter().find(|buffer| {
+            buffer.view_id != current_view && state.unsaved_views.contains(&buffer.view_id)
+        }) {
+            Some(buffer) => Err(format_err!(
+                "No write since last change for buffer \"{}\" (add ! to override)",
+                buffer.path
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn ensure_view_saved(&self) -> Result<(), Error> {
+        let state = self.state.lock().unwrap();
+        if state.unsaved_views.contains(&self.view_id) {
+            return Err(format_err!(
+                "No write since last change (add ! to override)"
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn show_command_line(&mut self) {
+        let (prompt, line) = match (self.mode, self.search_direction) {
+            (Mode::Search, SearchDirection::Forward) => ("/", &self.search_line),
+            (Mode::Search, SearchDirection::Backward) => ("?", &self.search_line),
+            _ => (":", &self.command_line),
+        };
+
+        self.front_event_writer
+            .send(ClientMessage::ShowCommandLine {
+                prompt: prompt.to_owned(),
+                content: line.content(),
+                cursor: line.cursor(),
+            });
+    }
+
+    /// Feed a keystroke either to the count prefix or to the current mode
+    /// keymap.
+    fn handle_keystroke(&mut self, key: KeyStroke) -> Vec<Binding> {
+        if self.accumulate_count(key) {
+            return Vec::new();
+        }
+
+        self.mode_actions().get_action_from_keystroke(key)
+    }
+
+    /// Give the action to run for a binding of the current mode, if any.
+    fn binding_action(&mut self, binding: Binding) -> Option<Action> {
+        match binding {
+            Binding::Action(action) => Some(action),
+            Binding::Unbound(key) if self.mode == Mode::Insert => {
+                Some(Action::InsertKeyStroke(key))
+            }
+            // An invalid motion cancels the operator.
+            Binding::Unbound(_) if self.mode == Mode::OperatorPending => {
+                Some(Action::SwitchToNormalMode)
+            }
+            Binding::Unbound(_) => {
+                // An invalid key cancels the count.
+                self.count = None;
+                None
+            }
+        }
+    }
+
+    /// Add the key to the count prefix if it is a digit.
+    ///
+    /// The count can only start in normal, visual and operator pending modes,
+    /// outside of a key sequence. A `0` can't start a count in order to be
+    /// usable as a binding.
+    fn accumulate_count(&mut self, key: KeyStroke) -> bool {
+        match self.mode {
+            Mode::Normal | Mode::Visual | Mode::OperatorPending => (),
+            _ => return false,
+        }
+
+        if self.mode_actions().is_pending() {
+            return false;
+        }
+
+        let digit = match key {
+            KeyStroke::Char(c) => match c.to_digit(10) {
+                Some(0) if self.count.is_none() => return false,
+                Some(digit) => digit,
+                None => return false,
+            },
+            _ => return false,
+        };
+
+        let count = self.count.unwrap_or(0);
+        self.count = Some((count * 10 + digit).min(MAX_COUNT));
+
+        true
+    }
+
+    fn mode_actions(&mut self) -> &mut ModeActions {
+        match self.mode {
+            Mode::Normal => &mut self.normal_mode,
+            Mode::Insert => &mut self.insert_mode,
+            Mode::Visual => &mut self.visual_mode,
+            Mode::Action => &mut self.action_mode,
+            Mode::OperatorPending => &mut self.operator_pending_mode,
+            Mode::Command | Mode::Search => &mut self.prompt_mode,
+        }
+    }
+
+    fn switch_mode(&mut self, mode: Mode) {
+        // The keys pending for the previous mode have no meaning in the
+        // new one.
+        self.mode_actions().clear_pending();
+        self.count = None;
+
+        let is_prompt = |mode| mode == Mode::Command || mode == Mode::Search;
+        if is_prompt(self.mode) && !is_prompt(mode) {
+            self.front_event_writer
+                .send(ClientMessage::HideCommandLine {});
+        }
+
+        self.mode = mode;
+    }
+
+    /// Display the count and the keys of the uncompleted sequence inside the
+    /// status bar.
+    fn update_pending_keys(&mut self) {
+        let mut pending_keys = self.count.map(|c| c.to_string()).unwrap_or_default();
+        pending_keys.extend(
+            self.mode_actions()
+                .pending_keys()
+                .iter()
+                .map(|key| key.to_description()),
+        );
+
+        if pending_keys == self.displayed_pending_keys {
+            return;
+        }
+
+        self.front_event_writer
+            .send(ClientMessage::UpdateStatusItem {
+                key: String::from("pending-keys"),
+                value: pending_keys.clone(),
+            });
+        self.displayed_pending_keys = pending_keys;
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Config;
+    use std::time::Duration;
+
+    use super::keyboard::{KeyStroke, Keyboard};
+    use super::mode_actions::Binding;
+    use super::{Action, Config, InputController, MAX_COUNT};
+    use crate::core::{self, Reader};
+    use crate::state::SharedState;
+
+    /// A keyboard without any key, the keys are given to the
+    /// InputController by the tests.
+    struct NoKeyboard;
+
+    impl Keyboard for NoKeyboard {
+        fn get_next_keystroke(&mut self) -> Option<KeyStroke> {
+            None
+        }
+
+        fn get_next_keystroke_before(&mut self, _timeout: Duration) -> Option<KeyStroke> {
+            None
+        }
+    }
+
+    /// The returned reader must be kept alive, it receives the messages sent
+    /// to the EventController.
+    fn input_controller() -> (InputController, Reader) {
+        let (writer, reader) = core::client_channel();
+        let controller = InputController::new(
+            Box::new(NoKeyboard),
+            writer,
+            &Config::default(),
+            SharedState::default(),
+        );
+
+        (controller, reader)
+    }
+
+    fn type_keys(controller: &mut InputController, keys: &str) -> Vec<Binding> {
+        keys.chars()
+            .flat_map(|c| controller.handle_keystroke(KeyStroke::Char(c)))
+            .collect()
+    }
+
+    #[test]
+    fn test_count_does_not_start_with_zero() {
+        let (mut controller, _reader) = input_controller();
+
+        assert_eq!(
+            vec![Binding::Action(Action::MoveToLineStart)],
+            type_keys(&mut controller, "0")
+        );
+        assert_eq!(None, controller.count);
+
+        assert!(type_keys(&mut controller, "10").is_empty());
+        assert_eq!(Some(10), controller.count);
+    }
+
+    #[test]
+    fn test_count_is_capped() {
+        let (mut controller, _reader) = input_controller();
+
+        assert!(type_keys(&mut controller, "123456").is_empty());
+        assert_eq!(Some(MAX_COUNT), controller.count);
+    }
+
+    #[test]
+    fn test_invalid_key_cancels_the_count() {
+        let (mut controller, _reader) = input_controller();
+
+        type_keys(&mut controller, "5");
+        let bindings = controller.handle_keystroke(KeyStroke::KeyF(12));
+        assert_eq!(vec![Binding::Unbound(KeyStroke::KeyF(12))], bindings);
+
+        assert_eq!(None, controller.binding_action(bindings[0]));
+        assert_eq!(None, controller.count);
+    }
 
     #[test]
     fn test_config_deserialization() {
@@ -179,5 +1260,4 @@ mod tests {
             config.visual_mode[&String::from("move_down")]
         );
     }
-
 }
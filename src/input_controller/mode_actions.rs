@@ -4,8 +4,40 @@ use super::actions::Action;
 use super::keyboard::KeyStroke;
 use super::Mode;
 
+/// The result of the resolution of some keystrokes.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Binding {
+    /// The keys are bound to this action.
+    Action(Action),
+    /// The key is not bound to any action.
+    Unbound(KeyStroke),
+}
+
+/// A node of the keymap prefix tree.
+///
+/// Each node is reached by a sequence of keys and can have an action bound
+/// to this sequence as well as some longer sequences starting with it.
+#[derive(Debug, Default)]
+struct KeymapNode {
+    action: Option<Action>,
+    children: HashMap<KeyStroke, KeymapNode>,
+}
+
+impl KeymapNode {
+    fn insert(&mut self, keys: &[KeyStroke], action: Action) {
+        match keys.split_first() {
+            Some((key, rest)) => self.children.entry(*key).or_default().insert(rest, action),
+            None => self.action = Some(action),
+        }
+    }
+}
+
 #[derive(Debug)]
-pub struct ModeActions(HashMap<KeyStroke, Action>);
+pub struct ModeActions {
+    keymap: KeymapNode,
+    /// The keys typed so far for a sequence not completed yet.
+    pending: Vec<KeyStroke>,
+}
 
 impl ModeActions {
     pub fn setup(mode: Mode, config_map: &HashMap<String, String>) -> Self {
@@ -14,11 +46,14 @@ impl ModeActions {
             Mode::Insert => defaults::DEFAULT_INSERT_MODE_ACTIONS.clone(),
             Mode::Visual => defaults::DEFAULT_VISUAL_MODE_ACTIONS.clone(),
             Mode::Action => defaults::DEFAULT_ACTION_MODE_ACTIONS.clone(),
+            Mode::OperatorPending => defaults::DEFAULT_OPERATOR_PENDING_MODE_ACTIONS.clone(),
+            // The prompt keys are handled by the `CommandLine` editor.
+            Mode::Command | Mode::Search => HashMap::new(),
         };
 
         for (action_desc, key_desc) in config_map.iter() {
-            let key = KeyStroke::from_description(&key_desc);
-            if key.is_none() {
+            let keys = KeyStroke::from_description(&key_desc);
+            if keys.is_none() {
                 continue;
             }
 
@@ -27,14 +62,101 @@ impl ModeActions {
                 continue;
             }
 
-            actions.insert(key.unwrap(), action.unwrap());
+            actions.insert(keys.unwrap(), action.unwrap());
         }
 
-        Self(actions)
+        let mut keymap = KeymapNode::default();
+        for (keys, action) in actions.iter() {
+            keymap.insert(keys, *action);
+        }
+
+        Self {
+            keymap,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Feed a new keystroke and retrieve the bindings resolved with it.
+    ///
+    /// The result is empty if the key is the start of a longer sequence. In
+    /// this case the key is kept pending until the next call or until a call
+    /// to `flush_pending`.
+    pub fn get_action_from_keystroke(&mut self, keystroke: KeyStroke) -> Vec<Binding> {
+        self.pending.push(keystroke);
+        self.resolve(false)
+    }
+
+    /// Resolve the pending keys without waiting for the end of the sequence.
+    ///
+    /// This is used when the user has stopped typing in the middle of a
+    /// sequence.
+    pub fn flush_pending(&mut self) -> Vec<Binding> {
+        self.resolve(true)
+    }
+
+    /// Drop the pending keys without resolving them.
+    pub fn clear_pending(&mut self) {
+        self.pending.clear();
+    }
+
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub fn pending_keys(&self) -> &[KeyStroke] {
+        &self.pending
     }
 
-    pub fn get_action_from_keystroke(&self, keystroke: KeyStroke) -> Option<Action> {
-        self.0.get(&keystroke).cloned()
+    /// Resolve the pending keys with the longest matching sequences.
+    ///
+    /// The keys which can't be the start of any sequence are returned as
+    /// `Binding::Unbound`. If `flush` is false and the last keys are the
+    /// start of a longer sequence, they stay pending.
+    fn resolve(&mut self, flush: bool) -> Vec<Binding> {
+        let keys: Vec<KeyStroke> = self.pending.drain(..).collect();
+        let mut bindings = Vec::new();
+
+        let mut idx = 0;
+        while idx < keys.len() {
+            let mut node = &self.keymap;
+            let mut longest_match = None;
+            let mut is_complete = true;
+
+            for (offset, key) in keys[idx..].iter().enumerate() {
+                match node.children.get(key) {
+                    Some(child) => {
+                        node = child;
+                        if let Some(action) = child.action {
+                            longest_match = Some((action, offset + 1));
+                        }
+                    }
+                    None => {
+                        is_complete = false;
+                        break;
+                    }
+                }
+            }
+
+            if is_complete && !flush && !node.children.is_empty() {
+                // All the remaining keys are the start of a longer sequence,
+                // wait for the next keys.
+                self.pending.extend_from_slice(&keys[idx..]);
+                break;
+            }
+
+            match longest_match {
+                Some((action, len)) => {
+                    bindings.push(Binding::Action(action));
+                    idx += len;
+                }
+                None => {
+                    bindings.push(Binding::Unbound(keys[idx]));
+                    idx += 1;
+                }
+            }
+        }
+
+        bindings
     }
 }
 
@@ -45,95 +167,260 @@ pub mod defaults {
     use super::super::keyboard::KeyStroke;
 
     lazy_static! {
-        pub static ref DEFAULT_NORMAL_MODE_ACTIONS: HashMap<KeyStroke, Action> = {
+        pub static ref DEFAULT_NORMAL_MODE_ACTIONS: HashMap<Vec<KeyStroke>, Action> = {
             let mut actions = HashMap::with_capacity(12);
 
             // The classic arrow keys.
-            actions.insert(KeyStroke::KeyUp, Action::MoveUp);
-            actions.insert(KeyStroke::KeyDown, Action::MoveDown);
-            actions.insert(KeyStroke::KeyLeft, Action::MoveLeft);
-            actions.insert(KeyStroke::KeyRight, Action::MoveRight);
+            actions.insert(vec![KeyStroke::KeyUp], Action::MoveUp);
+            actions.insert(vec![KeyStroke::KeyDown], Action::MoveDown);
+            actions.insert(vec![KeyStroke::KeyLeft], Action::MoveLeft);
+            actions.insert(vec![KeyStroke::KeyRight], Action::MoveRight);
 
             // The "vim like" keys.
-            actions.insert(KeyStroke::Char('k'), Action::MoveUp);
-            actions.insert(KeyStroke::Char('j'), Action::MoveDown);
-            actions.insert(KeyStroke::Char('h'), Action::MoveLeft);
-            actions.insert(KeyStroke::Char('l'), Action::MoveRight);
+            actions.insert(vec![KeyStroke::Char('k')], Action::MoveUp);
+            actions.insert(vec![KeyStroke::Char('j')], Action::MoveDown);
+            actions.insert(vec![KeyStroke::Char('h')], Action::MoveLeft);
+            actions.insert(vec![KeyStroke::Char('l')], Action::MoveRight);
+
+            actions.insert(vec![KeyStroke::Char('p')], Action::Paste);
+            actions.insert(vec![KeyStroke::Char('q')], Action::Quite);
+            actions.insert(vec![KeyStroke::Char('i')], Action::SwitchToInsertMode);
+            actions.insert(vec![KeyStroke::Char('v')], Action::SwitchToVisualMode);
+            actions.insert(vec![KeyStroke::KeySpace], Action::SwitchToActionMode);
+            actions.insert(vec![KeyStroke::Char(':')], Action::SwitchToCommandMode);
+
+            actions.insert(vec![KeyStroke::Char('/')], Action::SearchForward);
+            actions.insert(vec![KeyStroke::Char('?')], Action::SearchBackward);
+            actions.insert(vec![KeyStroke::Char('n')], Action::FindNext);
+            actions.insert(vec![KeyStroke::Char('N')], Action::FindPrevious);
+
+            // The windows keys, all prefixed by Ctrl-w.
+            let window_keys = [
+                ('s', Action::SplitWindow),
+                ('v', Action::VerticalSplitWindow),
+                ('h', Action::FocusLeftWindow),
+                ('j', Action::FocusDownWindow),
+                ('k', Action::FocusUpWindow),
+                ('l', Action::FocusRightWindow),
+                ('+', Action::IncreaseWindowHeight),
+                ('-', Action::DecreaseWindowHeight),
+                ('>', Action::IncreaseWindowWidth),
+                ('<', Action::DecreaseWindowWidth),
+                ('c', Action::CloseWindow),
+            ];
+            for (key, action) in window_keys.iter() {
+                actions.insert(vec![KeyStroke::Ctrl('w'), KeyStroke::Char(*key)], *action);
+            }
+
+            actions.insert(vec![KeyStroke::Char('g'), KeyStroke::Char('t')], Action::NextTab);
+            actions.insert(vec![KeyStroke::Char('g'), KeyStroke::Char('T')], Action::PreviousTab);
+
+            actions.insert(vec![KeyStroke::Char('o')], Action::InsertLineBelow);
+            actions.insert(vec![KeyStroke::Char('O')], Action::InsertLineAbove);
+
+            actions.insert(vec![KeyStroke::Char('w')], Action::MoveWordRight);
+            actions.insert(vec![KeyStroke::Char('W')], Action::MoveWordLeft);
+
+            actions.insert(vec![KeyStroke::Char('x')], Action::DeleteForward);
+            actions.insert(vec![KeyStroke::Char('X')], Action::DeleteBackward);
 
-            actions.insert(KeyStroke::Char('p'), Action::Paste);
-            actions.insert(KeyStroke::Char('q'), Action::Quite);
-            actions.insert(KeyStroke::Char('i'), Action::SwitchToInsertMode);
-            actions.insert(KeyStroke::Char('v'), Action::SwitchToVisualMode);
-            actions.insert(KeyStroke::KeySpace, Action::SwitchToActionMode);
+            actions.insert(vec![KeyStroke::Char('>')], Action::Indent);
+            actions.insert(vec![KeyStroke::Char('<')], Action::Outdent);
 
-            actions.insert(KeyStroke::Char('o'), Action::InsertLineBelow);
-            actions.insert(KeyStroke::Char('O'), Action::InsertLineAbove);
+            actions.insert(vec![KeyStroke::KeyHome], Action::MoveToLineStart);
+            actions.insert(vec![KeyStroke::KeyEnd], Action::MoveToLineEnd);
+            actions.insert(vec![KeyStroke::Ctrl('u')], Action::PageUp);
+            actions.insert(vec![KeyStroke::Ctrl('d')], Action::PageDown);
 
-            actions.insert(KeyStroke::Char('w'), Action::MoveWordRight);
-            actions.insert(KeyStroke::Char('W'), Action::MoveWordLeft);
+            actions.insert(vec![KeyStroke::Char('u')], Action::Undo);
+            actions.insert(vec![KeyStroke::Ctrl('r')], Action::Redo);
 
-            actions.insert(KeyStroke::Char('x'), Action::DeleteForward);
-            actions.insert(KeyStroke::Char('X'), Action::DeleteBackward);
+            actions.insert(vec![KeyStroke::Char('0')], Action::MoveToLineStart);
+            actions.insert(vec![KeyStroke::Char('$')], Action::MoveToLineEnd);
+            actions.insert(vec![KeyStroke::Char('g'), KeyStroke::Char('g')], Action::MoveToDocumentStart);
+            actions.insert(vec![KeyStroke::Char('G')], Action::MoveToDocumentEnd);
 
-            actions.insert(KeyStroke::Char('>'), Action::Indent);
-            actions.insert(KeyStroke::Char('<'), Action::Outdent);
+            // The operators waiting for a motion.
+            actions.insert(vec![KeyStroke::Char('d')], Action::DeleteOperator);
+            actions.insert(vec![KeyStroke::Char('c')], Action::ChangeOperator);
+            actions.insert(vec![KeyStroke::Char('y')], Action::YankOperator);
 
             actions
         };
 
-        pub static ref DEFAULT_ACTION_MODE_ACTIONS: HashMap<KeyStroke, Action> = {
+        pub static ref DEFAULT_ACTION_MODE_ACTIONS: HashMap<Vec<KeyStroke>, Action> = {
             let mut actions = HashMap::with_capacity(3);
 
-            actions.insert(KeyStroke::KeyEscape, Action::SwitchToNormalMode);
-            actions.insert(KeyStroke::Char('q'), Action::Quite);
-            actions.insert(KeyStroke::Char('w'), Action::WriteToFile);
+            actions.insert(vec![KeyStroke::KeyEscape], Action::SwitchToNormalMode);
+            actions.insert(vec![KeyStroke::Char('q')], Action::Quite);
+            actions.insert(vec![KeyStroke::Char('w')], Action::WriteToFile);
 
             actions
-    };
+        };
 
-        pub static ref DEFAULT_INSERT_MODE_ACTIONS: HashMap<KeyStroke, Action> = {
+        pub static ref DEFAULT_INSERT_MODE_ACTIONS: HashMap<Vec<KeyStroke>, Action> = {
             let mut actions = HashMap::with_capacity(12);
 
-            actions.insert(KeyStroke::KeyEscape, Action::SwitchToNormalMode);
-            actions.insert(KeyStroke::KeyBackSpace, Action::DeleteBackward);
-            actions.insert(KeyStroke::KeyDelete, Action::DeleteForward);
+            actions.insert(vec![KeyStroke::KeyEscape], Action::SwitchToNormalMode);
+            actions.insert(vec![KeyStroke::KeyBackSpace], Action::DeleteBackward);
+            actions.insert(vec![KeyStroke::KeyDelete], Action::DeleteForward);
 
             // The classic arrow keys.
-            actions.insert(KeyStroke::KeyUp, Action::MoveUp);
-            actions.insert(KeyStroke::KeyDown, Action::MoveDown);
-            actions.insert(KeyStroke::KeyLeft, Action::MoveLeft);
-            actions.insert(KeyStroke::KeyRight, Action::MoveRight);
-            actions.insert(KeyStroke::KeyPreviousPage, Action::PageUp);
-            actions.insert(KeyStroke::KeyNextPage, Action::PageDown);
+            actions.insert(vec![KeyStroke::KeyUp], Action::MoveUp);
+            actions.insert(vec![KeyStroke::KeyDown], Action::MoveDown);
+            actions.insert(vec![KeyStroke::KeyLeft], Action::MoveLeft);
+            actions.insert(vec![KeyStroke::KeyRight], Action::MoveRight);
+            actions.insert(vec![KeyStroke::KeyPreviousPage], Action::PageUp);
+            actions.insert(vec![KeyStroke::KeyNextPage], Action::PageDown);
+            actions.insert(vec![KeyStroke::KeyHome], Action::MoveToLineStart);
+            actions.insert(vec![KeyStroke::KeyEnd], Action::MoveToLineEnd);
 
             actions
-    };
+        };
 
-        pub static ref DEFAULT_VISUAL_MODE_ACTIONS: HashMap<KeyStroke, Action> = {
+        pub static ref DEFAULT_VISUAL_MODE_ACTIONS: HashMap<Vec<KeyStroke>, Action> = {
             let mut actions = HashMap::with_capacity(1);
 
-            actions.insert(KeyStroke::KeyEscape, Action::SwitchToNormalMode);
-            actions.insert(KeyStroke::Char('q'), Action::SwitchToNormalMode);
-            actions.insert(KeyStroke::Char('y'), Action::YankSelection);
-            actions.insert(KeyStroke::Char('d'), Action::DeleteSelection);
-            actions.insert(KeyStroke::Char('p'), Action::DeleteSelectionAndPaste);
+            actions.insert(vec![KeyStroke::KeyEscape], Action::SwitchToNormalMode);
+            actions.insert(vec![KeyStroke::Char('q')], Action::SwitchToNormalMode);
+            actions.insert(vec![KeyStroke::Char('y')], Action::YankSelection);
+            actions.insert(vec![KeyStroke::Char('d')], Action::DeleteSelection);
+            actions.insert(vec![KeyStroke::Char('p')], Action::DeleteSelectionAndPaste);
 
             // The classic arrow keys.
-            actions.insert(KeyStroke::KeyUp, Action::MoveUpAndSelect);
-            actions.insert(KeyStroke::KeyDown, Action::MoveDownAndSelect);
-            actions.insert(KeyStroke::KeyLeft, Action::MoveLeftAndSelect);
-            actions.insert(KeyStroke::KeyRight, Action::MoveRightAndSelect);
+            actions.insert(vec![KeyStroke::KeyUp], Action::MoveUpAndSelect);
+            actions.insert(vec![KeyStroke::KeyDown], Action::MoveDownAndSelect);
+            actions.insert(vec![KeyStroke::KeyLeft], Action::MoveLeftAndSelect);
+            actions.insert(vec![KeyStroke::KeyRight], Action::MoveRightAndSelect);
 
             // The "vim like" keys.
-            actions.insert(KeyStroke::Char('k'), Action::MoveUpAndSelect);
-            actions.insert(KeyStroke::Char('j'), Action::MoveDownAndSelect);
-            actions.insert(KeyStroke::Char('h'), Action::MoveLeftAndSelect);
-            actions.insert(KeyStroke::Char('l'), Action::MoveRightAndSelect);
+            actions.insert(vec![KeyStroke::Char('k')], Action::MoveUpAndSelect);
+            actions.insert(vec![KeyStroke::Char('j')], Action::MoveDownAndSelect);
+            actions.insert(vec![KeyStroke::Char('h')], Action::MoveLeftAndSelect);
+            actions.insert(vec![KeyStroke::Char('l')], Action::MoveRightAndSelect);
 
-            actions.insert(KeyStroke::Char('w'), Action::MoveWordRightAndSelect);
-            actions.insert(KeyStroke::Char('W'), Action::MoveWordLeftAndSelect);
+            actions.insert(vec![KeyStroke::Char('w')], Action::MoveWordRightAndSelect);
+            actions.insert(vec![KeyStroke::Char('W')], Action::MoveWordLeftAndSelect);
+
+            actions.insert(vec![KeyStroke::Char('0')], Action::MoveToLineStartAndSelect);
+            actions.insert(vec![KeyStroke::Char('$')], Action::MoveToLineEndAndSelect);
+            actions.insert(vec![KeyStroke::Char('g'), KeyStroke::Char('g')], Action::MoveToDocumentStartAndSelect);
+            actions.insert(vec![KeyStroke::Char('G')], Action::MoveToDocumentEndAndSelect);
+            actions.insert(vec![KeyStroke::KeyHome], Action::MoveToLineStartAndSelect);
+            actions.insert(vec![KeyStroke::KeyEnd], Action::MoveToLineEndAndSelect);
 
             actions
-    };
+        };
+
+        /// The motions used after an operator like `d`, `c` or `y`.
+        pub static ref DEFAULT_OPERATOR_PENDING_MODE_ACTIONS: HashMap<Vec<KeyStroke>, Action> = {
+            let mut actions = HashMap::with_capacity(20);
+
+            actions.insert(vec![KeyStroke::KeyEscape], Action::SwitchToNormalMode);
+
+            // The classic arrow keys.
+            actions.insert(vec![KeyStroke::KeyUp], Action::MoveUpAndSelect);
+            actions.insert(vec![KeyStroke::KeyDown], Action::MoveDownAndSelect);
+            actions.insert(vec![KeyStroke::KeyLeft], Action::MoveLeftAndSelect);
+            actions.insert(vec![KeyStroke::KeyRight], Action::MoveRightAndSelect);
+
+            // The "vim like" keys.
+            actions.insert(vec![KeyStroke::Char('k')], Action::MoveUpAndSelect);
+            actions.insert(vec![KeyStroke::Char('j')], Action::MoveDownAndSelect);
+            actions.insert(vec![KeyStroke::Char('h')], Action::MoveLeftAndSelect);
+            actions.insert(vec![KeyStroke::Char('l')], Action::MoveRightAndSelect);
+
+            actions.insert(vec![KeyStroke::Char('w')], Action::MoveWordRightAndSelect);
+            actions.insert(vec![KeyStroke::Char('W')], Action::MoveWordLeftAndSelect);
+
+            actions.insert(vec![KeyStroke::Char('0')], Action::MoveToLineStartAndSelect);
+            actions.insert(vec![KeyStroke::Char('$')], Action::MoveToLineEndAndSelect);
+            actions.insert(vec![KeyStroke::Char('g'), KeyStroke::Char('g')], Action::MoveToDocumentStartAndSelect);
+            actions.insert(vec![KeyStroke::Char('G')], Action::MoveToDocumentEndAndSelect);
+
+            // The text objects.
+            actions.insert(vec![KeyStroke::Char('i'), KeyStroke::Char('w')], Action::SelectInnerWord);
+
+            // An operator repeated applies on the whole line, like `dd`.
+            actions.insert(vec![KeyStroke::Char('d')], Action::SelectLine);
+            actions.insert(vec![KeyStroke::Char('c')], Action::SelectLine);
+            actions.insert(vec![KeyStroke::Char('y')], Action::SelectLine);
+
+            actions
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::super::actions::Action;
+    use super::super::keyboard::KeyStroke;
+    use super::super::Mode;
+    use super::{Binding, ModeActions};
+
+    fn setup_with(action: &str, keys: &str) -> ModeActions {
+        let mut config = HashMap::new();
+        config.insert(String::from(action), String::from(keys));
+
+        ModeActions::setup(Mode::Normal, &config)
+    }
+
+    #[test]
+    fn test_single_key_is_resolved_immediately() {
+        let mut actions = setup_with("paste", "gp");
+
+        assert_eq!(
+            vec![Binding::Action(Action::MoveDown)],
+            actions.get_action_from_keystroke(KeyStroke::Char('j'))
+        );
+        assert!(!actions.is_pending());
+    }
+
+    #[test]
+    fn test_sequence_waits_for_its_last_key() {
+        let mut actions = setup_with("paste", "gp");
+
+        assert!(actions
+            .get_action_from_keystroke(KeyStroke::Char('g'))
+            .is_empty());
+        assert_eq!(&[KeyStroke::Char('g')], actions.pending_keys());
+
+        assert_eq!(
+            vec![Binding::Action(Action::Paste)],
+            actions.get_action_from_keystroke(KeyStroke::Char('p'))
+        );
+        assert!(!actions.is_pending());
+    }
+
+    #[test]
+    fn test_ambiguous_sequence_resolves_the_shortest_on_flush() {
+        let mut actions = setup_with("paste", "jj");
+
+        assert!(actions
+            .get_action_from_keystroke(KeyStroke::Char('j'))
+            .is_empty());
+
+        assert_eq!(
+            vec![Binding::Action(Action::MoveDown)],
+            actions.flush_pending()
+        );
+        assert!(!actions.is_pending());
+    }
+
+    #[test]
+    fn test_broken_sequence_replays_the_keys() {
+        let mut actions = setup_with("paste", "gp");
+
+        actions.get_action_from_keystroke(KeyStroke::Char('g'));
+
+        assert_eq!(
+            vec![
+                Binding::Unbound(KeyStroke::Char('g')),
+                Binding::Action(Action::MoveDown)
+            ],
+            actions.get_action_from_keystroke(KeyStroke::Char('j'))
+        );
     }
 }
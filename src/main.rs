@@ -6,6 +6,7 @@ extern crate xi_rpc;
 extern crate log;
 extern crate chrono;
 extern crate fern;
+extern crate regex;
 #[macro_use]
 extern crate clap;
 extern crate dirs;
@@ -16,8 +17,10 @@ extern crate serde_derive;
 extern crate failure;
 #[macro_use]
 extern crate lazy_static;
+extern crate libc;
 extern crate termion;
 extern crate toml;
+extern crate unicode_width;
 #[cfg(feature = "tracing")]
 extern crate xi_trace;
 
@@ -29,6 +32,11 @@ mod core;
 mod event_controller;
 mod input_controller;
 mod logging;
+mod record;
+mod server;
+mod signal;
+mod split;
+mod state;
 #[cfg(feature = "tracing")]
 mod trace;
 
@@ -36,17 +44,22 @@ use std::cell::RefCell;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::stdin;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 use std::rc::Rc;
 use std::thread;
 
+use crate::core::{CoreConfig, Transport};
+use crate::record::Recorder;
 use event_controller::style::TermionStyles;
 use event_controller::window::TermionLayout;
 use event_controller::{EventController, Styles};
 use input_controller::keyboard::TermionKeyboard;
 use input_controller::{Config, InputController};
+use state::SharedState;
 
 use failure::Error;
+use serde::de::DeserializeOwned;
 
 use xi_rpc::{Peer, RpcLoop};
 
@@ -58,63 +71,147 @@ fn setup_logger() {
     logging::setup(&logging_path).expect("failed to set the logger")
 }
 
-fn setup_config(core: &dyn Peer) -> Result<Config, Error> {
-    let config_dir = dirs::config_dir().ok_or_else(|| format_err!("config dir not found"))?;
+/// The settings of `config.toml`, the keymaps being in `keyboard.toml`.
+#[derive(Debug, Default, Deserialize)]
+struct Settings {
+    #[serde(default)]
+    core: CoreConfig,
+}
 
-    let mut xi_config_dir = config_dir.clone();
-    xi_config_dir.push("xi");
-    core.send_rpc_notification(
-        "client_started",
-        &json!({ "config_dir": xi_config_dir.to_str().unwrap(), }),
-    );
+/// Read the configuration file of vixi with the given name, or give the
+/// default configuration if there is no such file.
+fn read_config_file<T: DeserializeOwned + Default>(name: &str) -> Result<T, Error> {
+    let config_dir = dirs::config_dir().ok_or_else(|| format_err!("config dir not found"))?;
 
-    let vixi_config_dir = config_dir.join("vixi");
-    let vixi_keyboard_config_file = vixi_config_dir.join("keyboard.toml");
+    let vixi_config_file = config_dir.join("vixi").join(name);
 
-    let config = if vixi_keyboard_config_file.is_file() {
-        let mut keyboard_config_file = File::open(vixi_keyboard_config_file)?;
-        let mut keyboard_config_contents = String::new();
-        keyboard_config_file.read_to_string(&mut keyboard_config_contents)?;
-        toml::from_str(&keyboard_config_contents)?
+    let config = if vixi_config_file.is_file() {
+        let mut config_file = File::open(vixi_config_file)?;
+        let mut config_contents = String::new();
+        config_file.read_to_string(&mut config_contents)?;
+        toml::from_str(&config_contents)?
     } else {
-        Config::default()
+        T::default()
     };
 
     Ok(config)
 }
 
+fn setup_config() -> Result<(Config, Settings), Error> {
+    let config = read_config_file("keyboard.toml")?;
+    let settings = read_config_file("config.toml")?;
+
+    Ok((config, settings))
+}
+
+fn send_client_started(core: &dyn Peer) -> Result<(), Error> {
+    let config_dir = dirs::config_dir().ok_or_else(|| format_err!("config dir not found"))?;
+
+    let xi_config_dir = config_dir.join("xi");
+    core.send_rpc_notification(
+        "client_started",
+        &json!({ "config_dir": xi_config_dir.to_str().unwrap(), }),
+    );
+
+    Ok(())
+}
+
 fn main() {
     let matches = cli::build().get_matches();
 
+    setup_logger();
+
+    if let Some(socket_path) = matches.value_of("server") {
+        if let Err(err) = server::run(Path::new(socket_path)) {
+            println!("failed to run the server: {}", err);
+            exit(1);
+        }
+        return;
+    }
+
+    if let Some(recording_path) = matches.value_of("replay") {
+        match record::replay(Path::new(recording_path)) {
+            Ok(screen) => println!("{}", screen),
+            Err(err) => {
+                println!("failed to replay {}: {}", recording_path, err);
+                exit(1);
+            }
+        }
+        return;
+    }
+
     let file_path = matches
         .value_of("file")
         .expect("failed to retrieve cli value");
 
-    setup_logger();
-
     #[cfg(feature = "tracing")]
     trace::start_tracer();
 
-    let (client_to_core_writer, core_to_client_reader, client_to_client_writer) =
-        core::start_xi_core();
-    let mut front_event_loop = RpcLoop::new(client_to_core_writer);
+    // The other threads inherit the signal mask, it must be set before
+    // spawning them.
+    signal::block_resize_signal();
 
-    let raw_peer = front_event_loop.get_raw_peer();
-    let config = match setup_config(&raw_peer) {
-        Ok(config) => config,
+    let (config, settings) = match setup_config() {
+        Ok(configs) => configs,
         Err(err) => {
             println!("failed to load the configuration: {}", err);
             exit(1);
         }
     };
 
+    // The command line takes precedence over the configuration file.
+    let transport = match (matches.value_of("connect"), matches.value_of("core")) {
+        (Some(socket_path), _) => Transport::Socket(PathBuf::from(socket_path)),
+        (None, Some(path)) => Transport::Process(PathBuf::from(path)),
+        (None, None) => match settings.core.path {
+            Some(ref path) => Transport::Process(path.clone()),
+            None => Transport::Embedded,
+        },
+    };
+
+    let recorder = matches.value_of("record").map(|recording_path| {
+        let (width, height) = termion::terminal_size().unwrap_or((80, 24));
+        Recorder::create(Path::new(recording_path), width.into(), height.into()).unwrap_or_else(
+            |err| {
+                println!("failed to start the recording: {}", err);
+                exit(1);
+            },
+        )
+    });
+
+    let (client_to_core_writer, mut core_to_client_reader, client_to_client_writer, core_thread) =
+        match core::start_xi_core(&transport, recorder.as_ref()) {
+            Ok(channels) => channels,
+            Err(err) => {
+                println!("failed to start xi-core: {}", err);
+                exit(1);
+            }
+        };
+    signal::watch_resize(client_to_client_writer.clone());
+    let mut front_event_loop = RpcLoop::new(client_to_core_writer);
+
+    let raw_peer = front_event_loop.get_raw_peer();
+    if let Err(err) = send_client_started(&raw_peer) {
+        println!("failed to start the client: {}", err);
+        exit(1);
+    }
+
+    let state = SharedState::default();
+    let event_controller_state = state.clone();
+
+    let client_messages = core_to_client_reader.client_messages();
     let child = thread::spawn(move || {
         let layout = TermionLayout::new();
 
         let styles: Rc<RefCell<Box<dyn Styles>>> =
             Rc::new(RefCell::new(Box::new(TermionStyles::new())));
 
-        let mut event_handler = EventController::new(Box::new(layout), styles.clone());
+        let mut event_handler = EventController::new(
+            Box::new(layout),
+            styles.clone(),
+            event_controller_state,
+            client_messages,
+        );
         front_event_loop
             .mainloop(|| core_to_client_reader, &mut event_handler)
             .unwrap();
@@ -124,6 +221,7 @@ fn main() {
         Box::new(TermionKeyboard::from_reader(stdin())),
         client_to_client_writer,
         &config,
+        state,
     );
 
     if let Err(err) = input_controller.open_file(&raw_peer, file_path) {
@@ -136,8 +234,16 @@ fn main() {
         exit(1);
     }
 
+    // The EventController stops after the messages sent before the
+    // shutdown, the writes it sends to the core included.
     child.join().unwrap();
 
+    // Dropping the last writer closes the input of the core, it stops once
+    // it handled everything.
+    input_controller.shutdown(&raw_peer);
+    drop(raw_peer);
+    core_thread.join().unwrap();
+
     #[cfg(feature = "tracing")]
     trace::write_trace_dump_into("./trace.out")
 }
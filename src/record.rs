@@ -0,0 +1,128 @@
+//! Record the messages crossing the channels of `core.rs` and replay them
+//! into an EventController drawing into a screen kept in memory.
+//!
+//! A recording is a file holding one JSON object per line, like
+//! `{"direction":"from_core","timestamp":12,"message":{...}}`. It starts with
+//! a "resize" client message giving the size of the terminal.
+//!
+//! The messages of the core and of the InputController are recorded when
+//! the EventController reads them, so the replay gives them in the same
+//! order even if they were written concurrently.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::core::{ClientMessage, Message};
+use crate::event_controller::window::memory;
+
+use failure::Error;
+use serde_json::Value;
+
+/// The size of the screen replaying a recording without any size.
+const DEFAULT_SIZE: (u32, u32) = (80, 24);
+
+/// Where a message goes.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    ToCore,
+    FromCore,
+    /// From the InputController to the EventController.
+    Client,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordedMessage {
+    direction: Direction,
+    /// The number of milliseconds since the start of the recording.
+    timestamp: u64,
+    message: Value,
+}
+
+/// Write the recorded messages into a file, shared by all the channels.
+#[derive(Clone)]
+pub struct Recorder {
+    file: Arc<Mutex<File>>,
+    start: Instant,
+}
+
+impl Recorder {
+    /// Start a recording of a terminal of the given size.
+    pub fn create(path: &Path, width: u32, height: u32) -> Result<Self, Error> {
+        let file = File::create(path)
+            .map_err(|err| format_err!("failed to create {}: {}", path.display(), err))?;
+
+        let recorder = Self {
+            file: Arc::new(Mutex::new(file)),
+            start: Instant::now(),
+        };
+
+        let size = ClientMessage::Resize { width, height };
+        recorder.record(Direction::Client, &serde_json::to_string(&size)?);
+
+        Ok(recorder)
+    }
+
+    /// Record a raw JSON message. A message which is not valid JSON is kept
+    /// as a string.
+    pub fn record(&self, direction: Direction, raw: &str) {
+        let elapsed = self.start.elapsed();
+        let record = RecordedMessage {
+            direction,
+            timestamp: elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis()),
+            message: serde_json::from_str(raw).unwrap_or_else(|_| Value::from(raw)),
+        };
+
+        let mut line = match serde_json::to_string(&record) {
+            Ok(line) => line,
+            Err(err) => {
+                error!("failed to record a message: {}", err);
+                return;
+            }
+        };
+        line.push('\n');
+
+        // A single write per message, the channels record concurrently.
+        if let Err(err) = self.file.lock().unwrap().write_all(line.as_bytes()) {
+            error!("failed to record a message: {}", err);
+        }
+    }
+}
+
+/// Feed the messages read by the EventController during the recording at
+/// `path`, the core ones and the client ones, into a new EventController.
+///
+/// The screen takes the recorded sizes of the terminal. It gives the text of
+/// the screen at the end of the replay.
+pub fn replay(path: &Path) -> Result<String, Error> {
+    let file = File::open(path)
+        .map_err(|err| format_err!("failed to open {}: {}", path.display(), err))?;
+
+    let mut messages = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let record: RecordedMessage = serde_json::from_str(&line?)?;
+        match record.direction {
+            Direction::ToCore => (),
+            // The answers to the requests of the InputController don't go
+            // to the EventController.
+            Direction::FromCore if is_response(&record.message) => (),
+            Direction::FromCore => messages.push(Message::Core(record.message.to_string())),
+            Direction::Client => {
+                let message: ClientMessage = serde_json::from_value(record.message)?;
+                messages.push(Message::Client(message));
+            }
+        }
+    }
+
+    let (width, height) = DEFAULT_SIZE;
+    let screen = memory::draw(messages, width, height)
+        .map_err(|err| format_err!("failed to replay {}: {}", path.display(), err))?;
+    Ok(screen.text())
+}
+
+fn is_response(message: &Value) -> bool {
+    message.get("id").is_some() && message.get("method").is_none()
+}
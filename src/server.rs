@@ -0,0 +1,509 @@
+//! The server mode, sharing one xi-core between several clients connected
+//! over a Unix domain socket.
+//!
+//! The buffers, their undo history and the plugins live inside the shared
+//! core while each client keeps its own views. The server routes the
+//! messages of the core to the client owning their view.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::mem;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::core::{self, Writer};
+
+use failure::Error;
+use serde_json::Value;
+
+type ClientID = usize;
+
+/// The notifications of the core for all the clients which are given again
+/// to the clients connecting later.
+const REPLAYED_NOTIFICATIONS: [&str; 4] = [
+    "def_style",
+    "available_themes",
+    "theme_changed",
+    "available_languages",
+];
+
+/// A request of a client waiting for the answer of the core.
+#[derive(Debug)]
+struct PendingRequest {
+    client: ClientID,
+    /// The id given by the client, replaced by an unique one for the core.
+    id: Value,
+    method: String,
+}
+
+/// Decide where each message goes.
+#[derive(Debug, Default)]
+struct Router {
+    clients: BTreeSet<ClientID>,
+    next_client_id: ClientID,
+    /// The client owning each view.
+    views: HashMap<String, ClientID>,
+    requests: HashMap<u64, PendingRequest>,
+    next_request_id: u64,
+    /// The core can be started only once.
+    client_started: bool,
+    /// The client answering the requests of the core.
+    last_client: Option<ClientID>,
+    /// The notifications of a view received before the answer of the
+    /// "new_view" request giving its owner.
+    orphans: Vec<Value>,
+    /// The last notifications of each kind replayed to the new clients.
+    replayed: Vec<Value>,
+}
+
+impl Router {
+    fn connect(&mut self) -> ClientID {
+        let client = self.next_client_id;
+        self.next_client_id += 1;
+        self.clients.insert(client);
+
+        client
+    }
+
+    /// Forget a client and give the messages closing its views.
+    fn disconnect(&mut self, client: ClientID) -> Vec<Value> {
+        self.clients.remove(&client);
+        self.requests.retain(|_, request| request.client != client);
+        if self.last_client == Some(client) {
+            self.last_client = None;
+        }
+
+        let views: Vec<String> = self
+            .views
+            .iter()
+            .filter(|(_, owner)| **owner == client)
+            .map(|(view_id, _)| view_id.to_owned())
+            .collect();
+
+        views
+            .into_iter()
+            .map(|view_id| {
+                self.views.remove(&view_id);
+                json!({"method": "close_view", "params": {"view_id": view_id}})
+            })
+            .collect()
+    }
+
+    /// Route a message of a client, giving the message to send to the core
+    /// if any.
+    fn route_client_message(&mut self, client: ClientID, mut message: Value) -> Option<Value> {
+        self.last_client = Some(client);
+
+        let method = match message.get("method").and_then(Value::as_str) {
+            Some(method) => method.to_owned(),
+            // The answer to a request of the core.
+            None => return Some(message),
+        };
+
+        if let Some(id) = message.get("id").cloned() {
+            let core_id = self.next_request_id;
+            self.next_request_id += 1;
+            self.requests
+                .insert(core_id, PendingRequest { client, id, method });
+
+            message["id"] = json!(core_id);
+            return Some(message);
+        }
+
+        match method.as_str() {
+            "client_started" if self.client_started => None,
+            "client_started" => {
+                self.client_started = true;
+                Some(message)
+            }
+            // The core outlives its clients.
+            "exit" => None,
+            "close_view" => {
+                if let Some(view_id) = message["params"]["view_id"].as_str() {
+                    self.views.remove(view_id);
+                }
+                Some(message)
+            }
+            _ => Some(message),
+        }
+    }
+
+    /// Route a message of the core, giving the clients to send it to.
+    fn route_core_message(&mut self, mut message: Value) -> Vec<(ClientID, Value)> {
+        let is_request = message.get("method").is_some();
+
+        match message.get("id").and_then(Value::as_u64) {
+            Some(id) if !is_request => {
+                let request = match self.requests.remove(&id) {
+                    Some(request) => request,
+                    None => {
+                        warn!("answer to an unknown request: {}", message);
+                        return Vec::new();
+                    }
+                };
+
+                if request.method == "new_view" {
+                    if let Some(view_id) = message["result"].as_str() {
+                        self.views.insert(view_id.to_owned(), request.client);
+                    }
+                }
+
+                message["id"] = request.id;
+                let mut routes = vec![(request.client, message)];
+                if !self.is_waiting_for_view() {
+                    for orphan in mem::take(&mut self.orphans) {
+                        routes.extend(self.route_core_message(orphan));
+                    }
+                }
+
+                return routes;
+            }
+            Some(_) => {
+                let client = self
+                    .last_client
+                    .or_else(|| self.clients.iter().next().cloned());
+
+                return client
+                    .map(|client| vec![(client, message)])
+                    .unwrap_or_default();
+            }
+            None => (),
+        }
+
+        let view_id = message["params"]["view_id"].as_str().map(str::to_owned);
+        match view_id {
+            Some(ref view_id) if self.views.contains_key(view_id) => {
+                vec![(self.views[view_id], message)]
+            }
+            Some(_) if self.is_waiting_for_view() => {
+                self.orphans.push(message);
+                Vec::new()
+            }
+            // A view already closed, the clients would create it again.
+            Some(view_id) => {
+                warn!("notification for the unknown view {}: {}", view_id, message);
+                Vec::new()
+            }
+            None => {
+                self.remember(&message);
+                self.clients
+                    .iter()
+                    .map(|client| (*client, message.clone()))
+                    .collect()
+            }
+        }
+    }
+
+    /// Keep a notification for all the clients in order to replay it.
+    ///
+    /// It replaces the previous one with the same method and the same id,
+    /// like the styles.
+    fn remember(&mut self, message: &Value) {
+        match message["method"].as_str() {
+            Some(method) if REPLAYED_NOTIFICATIONS.contains(&method) => (),
+            _ => return,
+        }
+
+        let key = |message: &Value| (message["method"].clone(), message["params"]["id"].clone());
+        match self
+            .replayed
+            .iter_mut()
+            .find(|replayed| key(replayed) == key(message))
+        {
+            Some(replayed) => *replayed = message.clone(),
+            None => self.replayed.push(message.clone()),
+        }
+    }
+
+    /// Give the notifications to send to a new client before any other
+    /// message, the styles for instance.
+    fn replayed_notifications(&self) -> &[Value] {
+        &self.replayed
+    }
+
+    fn is_waiting_for_view(&self) -> bool {
+        self.requests
+            .values()
+            .any(|request| request.method == "new_view")
+    }
+}
+
+#[derive(Default)]
+struct Server {
+    router: Router,
+    /// The outgoing messages of each client, written into its socket by its
+    /// own thread in order to never block the routing.
+    queues: HashMap<ClientID, Sender<Value>>,
+}
+
+impl Server {
+    fn send_to_clients(&mut self, routes: Vec<(ClientID, Value)>) {
+        for (client, message) in routes {
+            // The writing thread of a client stops once the client is gone,
+            // the disconnection is handled by the thread reading the client.
+            if let Some(queue) = self.queues.get(&client) {
+                let _ = queue.send(message);
+            }
+        }
+    }
+}
+
+/// Write the message with a single write, the xi-rpc readers expect one
+/// message per line.
+fn send_line<W: Write>(dest: &mut W, message: &Value) -> Result<(), Error> {
+    let mut raw = serde_json::to_string(message)?;
+    raw.push('\n');
+    dest.write_all(raw.as_bytes())?;
+
+    Ok(())
+}
+
+/// Share an embedded core with the clients connecting to the Unix socket at
+/// `path`. It never returns unless the socket fails.
+pub fn run(path: &Path) -> Result<(), Error> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(format_err!(
+                "a server already listens on {}",
+                path.display()
+            ));
+        }
+
+        // The socket left by a previous server, any other file is kept.
+        if !fs::symlink_metadata(path)?.file_type().is_socket() {
+            return Err(format_err!("{} exists and is not a socket", path.display()));
+        }
+        fs::remove_file(path)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+    let (to_core, from_core) = core::start_headless_core();
+    let server = Arc::new(Mutex::new(Server::default()));
+
+    let core_server = server.clone();
+    thread::spawn(move || {
+        for raw in from_core.lines().filter_map(Result::ok) {
+            let message = match serde_json::from_str(&raw) {
+                Ok(message) => message,
+                Err(err) => {
+                    error!("invalid message from xi-core: {}", err);
+                    continue;
+                }
+            };
+
+            let mut server = core_server.lock().unwrap();
+            let routes = server.router.route_core_message(message);
+            server.send_to_clients(routes);
+        }
+    });
+
+    info!("listening on {}", path.display());
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let server = server.clone();
+        let to_core = to_core.clone();
+
+        thread::spawn(move || {
+            if let Err(err) = serve_client(stream, &server, to_core) {
+                error!("client error: {}", err);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+fn serve_client(
+    stream: UnixStream,
+    server: &Arc<Mutex<Server>>,
+    mut to_core: Writer,
+) -> Result<(), Error> {
+    let mut output = stream.try_clone()?;
+    let (queue, messages) = channel();
+    let client = {
+        let mut server = server.lock().unwrap();
+        let client = server.router.connect();
+        for message in server.router.replayed_notifications() {
+            // The receiver is still there.
+            queue.send(message.clone()).unwrap();
+        }
+        server.queues.insert(client, queue);
+        client
+    };
+    info!("client {} connected", client);
+
+    // The thread stops once the queue is removed from the server.
+    thread::spawn(move || {
+        for message in messages {
+            if let Err(err) = send_line(&mut output, &message) {
+                warn!("failed to send a message to the client {}: {}", client, err);
+                break;
+            }
+        }
+    });
+
+    for line in BufReader::new(stream).lines() {
+        let message = match serde_json::from_str(&line?) {
+            Ok(message) => message,
+            Err(err) => {
+                error!("invalid message from the client {}: {}", client, err);
+                continue;
+            }
+        };
+
+        let message = server
+            .lock()
+            .unwrap()
+            .router
+            .route_client_message(client, message);
+        if let Some(message) = message {
+            send_line(&mut to_core, &message)?;
+        }
+    }
+
+    info!("client {} disconnected", client);
+    let close_messages = {
+        let mut server = server.lock().unwrap();
+        server.queues.remove(&client);
+        server.router.disconnect(client)
+    };
+
+    for message in close_messages {
+        send_line(&mut to_core, &message)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use super::{run, Router};
+
+    #[test]
+    fn test_route_the_views_to_their_owner() {
+        let mut router = Router::default();
+        let first = router.connect();
+        let second = router.connect();
+
+        let request = json!({"id": 0, "method": "new_view", "params": {}});
+        let to_core = router.route_client_message(second, request).unwrap();
+        assert_eq!(json!(0), to_core["id"]);
+
+        // The update comes before the answer giving the owner.
+        let update = json!({"method": "update", "params": {"view_id": "view-id-1"}});
+        assert!(router.route_core_message(update.clone()).is_empty());
+
+        let routes = router.route_core_message(json!({"id": 0, "result": "view-id-1"}));
+        assert_eq!(
+            vec![
+                (second, json!({"id": 0, "result": "view-id-1"})),
+                (second, update.clone()),
+            ],
+            routes
+        );
+
+        let theme = json!({"method": "theme_changed", "params": {}});
+        assert_eq!(
+            vec![(first, theme.clone()), (second, theme.clone())],
+            router.route_core_message(theme)
+        );
+    }
+
+    #[test]
+    fn test_drop_the_notifications_of_unknown_views() {
+        let mut router = Router::default();
+        router.connect();
+
+        // The view has been closed.
+        let update = json!({"method": "update", "params": {"view_id": "view-id-1"}});
+        assert!(router.route_core_message(update).is_empty());
+    }
+
+    #[test]
+    fn test_replay_the_global_notifications() {
+        let mut router = Router::default();
+        router.connect();
+
+        let style = |id, fg| json!({"method": "def_style", "params": {"id": id, "fg_color": fg}});
+        let theme = json!({"method": "theme_changed", "params": {"name": "dark"}});
+        router.route_core_message(style(2, 10));
+        router.route_core_message(theme.clone());
+        router.route_core_message(style(3, 10));
+        router.route_core_message(style(2, 20));
+        router.route_core_message(json!({"method": "alert", "params": {"msg": "hello"}}));
+
+        assert_eq!(
+            vec![style(2, 20), theme, style(3, 10)],
+            router.replayed_notifications().to_vec()
+        );
+    }
+
+    #[test]
+    fn test_give_unique_request_ids() {
+        let mut router = Router::default();
+        let first = router.connect();
+        let second = router.connect();
+
+        let request = json!({"id": 3, "method": "new_view", "params": {}});
+        router.route_client_message(first, request.clone()).unwrap();
+        router.route_client_message(second, request).unwrap();
+
+        let routes = router.route_core_message(json!({"id": 1, "result": "view-id-2"}));
+        assert_eq!(
+            vec![(second, json!({"id": 3, "result": "view-id-2"}))],
+            routes
+        );
+    }
+
+    #[test]
+    fn test_share_the_core_lifetime() {
+        let mut router = Router::default();
+        let first = router.connect();
+        let second = router.connect();
+
+        let started = json!({"method": "client_started", "params": {}});
+        assert!(router
+            .route_client_message(first, started.clone())
+            .is_some());
+        assert!(router.route_client_message(second, started).is_none());
+
+        let exit = json!({"method": "exit", "params": {}});
+        assert!(router.route_client_message(first, exit).is_none());
+    }
+
+    #[test]
+    fn test_close_the_views_of_a_disconnected_client() {
+        let mut router = Router::default();
+        let client = router.connect();
+
+        let request = json!({"id": 0, "method": "new_view", "params": {}});
+        router.route_client_message(client, request).unwrap();
+        router.route_core_message(json!({"id": 0, "result": "view-id-1"}));
+
+        assert_eq!(
+            vec![json!({"method": "close_view", "params": {"view_id": "view-id-1"}})],
+            router.disconnect(client)
+        );
+        assert!(router.disconnect(client).is_empty());
+    }
+
+    #[test]
+    fn test_keep_the_files_which_are_not_sockets() {
+        let path = env::temp_dir().join(format!("vixi-test-{}.txt", process::id()));
+        fs::write(&path, "notes").unwrap();
+
+        assert!(run(&path).is_err());
+        assert_eq!("notes", fs::read_to_string(&path).unwrap());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
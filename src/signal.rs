@@ -0,0 +1,51 @@
+use std::mem;
+use std::ptr;
+use std::thread;
+
+use crate::core::{ClientMessage, ClientToClientWriter};
+
+/// Block the SIGWINCH signal for the current thread and for all the threads
+/// spawned by it, the signal is then only received by `watch_resize`.
+///
+/// It must be called before spawning any thread.
+pub fn block_resize_signal() {
+    let set = resize_signal_set();
+
+    unsafe {
+        libc::pthread_sigmask(libc::SIG_BLOCK, &set, ptr::null_mut());
+    }
+}
+
+/// Send a "resize" notification with the new size to the client each time
+/// the terminal is resized.
+pub fn watch_resize(mut client_to_client_writer: ClientToClientWriter) {
+    thread::spawn(move || {
+        let set = resize_signal_set();
+
+        loop {
+            let mut signal = 0;
+            let res = unsafe { libc::sigwait(&set, &mut signal) };
+            if res != 0 {
+                error!("failed to wait for the resize signal: {}", res);
+                return;
+            }
+
+            match termion::terminal_size() {
+                Ok((width, height)) => client_to_client_writer.send(ClientMessage::Resize {
+                    width: width.into(),
+                    height: height.into(),
+                }),
+                Err(err) => error!("failed to read the terminal size: {}", err),
+            }
+        }
+    });
+}
+
+fn resize_signal_set() -> libc::sigset_t {
+    unsafe {
+        let mut set: libc::sigset_t = mem::zeroed();
+        libc::sigemptyset(&mut set);
+        libc::sigaddset(&mut set, libc::SIGWINCH);
+        set
+    }
+}
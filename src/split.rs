@@ -0,0 +1,315 @@
+//! The layout of the panes, shared by the InputController editing it and
+//! the EventController drawing it.
+
+/// The smallest share of a split given to one of its panes, in percent.
+const MIN_RATIO: i16 = 10;
+/// The biggest share of a split given to one of its panes, in percent.
+const MAX_RATIO: i16 = 90;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SplitDirection {
+    /// The panes are stacked on top of each other, like `:split`.
+    Horizontal,
+    /// The panes are side by side, like `:vsplit`.
+    Vertical,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Direction {
+    Left,
+    Down,
+    Up,
+    Right,
+}
+
+impl Direction {
+    /// Give the split direction crossed by a move and if the move goes
+    /// toward the second pane of the splits.
+    fn axis(self) -> (SplitDirection, bool) {
+        match self {
+            Direction::Left => (SplitDirection::Vertical, false),
+            Direction::Right => (SplitDirection::Vertical, true),
+            Direction::Up => (SplitDirection::Horizontal, false),
+            Direction::Down => (SplitDirection::Horizontal, true),
+        }
+    }
+}
+
+/// The panes of the screen, each one displaying a view.
+///
+/// A view is displayed by at most one pane so the panes are identified by
+/// their view.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SplitTree {
+    Pane(String),
+    Split {
+        direction: SplitDirection,
+        /// The share of the space given to the first pane, in percent.
+        ratio: u8,
+        first: Box<SplitTree>,
+        second: Box<SplitTree>,
+    },
+}
+
+impl SplitTree {
+    pub fn new(view_id: &str) -> Self {
+        SplitTree::Pane(view_id.to_owned())
+    }
+
+    pub fn contains(&self, view_id: &str) -> bool {
+        match self {
+            SplitTree::Pane(id) => id == view_id,
+            SplitTree::Split { first, second, .. } => {
+                first.contains(view_id) || second.contains(view_id)
+            }
+        }
+    }
+
+    pub fn pane_count(&self) -> usize {
+        match self {
+            SplitTree::Pane(_) => 1,
+            SplitTree::Split { first, second, .. } => first.pane_count() + second.pane_count(),
+        }
+    }
+
+    /// Display `new_view_id` in the pane of `view_id`.
+    pub fn replace(&mut self, view_id: &str, new_view_id: &str) -> bool {
+        match self {
+            SplitTree::Pane(id) if id == view_id => {
+                *id = new_view_id.to_owned();
+                true
+            }
+            SplitTree::Pane(_) => false,
+            SplitTree::Split { first, second, .. } => {
+                first.replace(view_id, new_view_id) || second.replace(view_id, new_view_id)
+            }
+        }
+    }
+
+    /// Split the pane of `view_id` in two. The new pane displays
+    /// `new_view_id` and is placed above or on the left, like with Vim.
+    pub fn split(&mut self, view_id: &str, new_view_id: &str, direction: SplitDirection) -> bool {
+        match self {
+            SplitTree::Pane(id) if id == view_id => {
+                let old_pane = SplitTree::new(id);
+                *self = SplitTree::Split {
+                    direction,
+                    ratio: 50,
+                    first: Box::new(SplitTree::new(new_view_id)),
+                    second: Box::new(old_pane),
+                };
+                true
+            }
+            SplitTree::Pane(_) => false,
+            SplitTree::Split { first, second, .. } => {
+                first.split(view_id, new_view_id, direction)
+                    || second.split(view_id, new_view_id, direction)
+            }
+        }
+    }
+
+    /// Remove the pane of `view_id`, its sibling taking its space.
+    ///
+    /// It returns the view of the pane to focus instead, or `None` if the
+    /// pane is not found or is the last one.
+    pub fn close(&mut self, view_id: &str) -> Option<String> {
+        let sibling = match self {
+            SplitTree::Pane(_) => return None,
+            SplitTree::Split { first, second, .. } => {
+                if first.is_pane_of(view_id) {
+                    (**second).clone()
+                } else if second.is_pane_of(view_id) {
+                    (**first).clone()
+                } else {
+                    return first.close(view_id).or_else(|| second.close(view_id));
+                }
+            }
+        };
+
+        *self = sibling;
+        Some(self.edge_view(SplitDirection::Vertical, false))
+    }
+
+    /// Give the view of the pane next to the one of `view_id` in the given
+    /// direction.
+    pub fn neighbour(&self, view_id: &str, direction: Direction) -> Option<String> {
+        let (axis, forward) = direction.axis();
+        self.find_neighbour(view_id, axis, forward).flatten()
+    }
+
+    /// Grow the pane of `view_id` along the split direction `axis`, or
+    /// shrink it with a negative `delta`.
+    ///
+    /// The `delta` is a percent of the closest split in this direction.
+    pub fn resize(&mut self, view_id: &str, axis: SplitDirection, delta: i16) -> bool {
+        self.resize_pane(view_id, axis, delta).unwrap_or(false)
+    }
+
+    fn is_pane_of(&self, view_id: &str) -> bool {
+        match self {
+            SplitTree::Pane(id) => id == view_id,
+            SplitTree::Split { .. } => false,
+        }
+    }
+
+    /// Give the view of the pane on the edge of the tree, the last one along
+    /// `axis` if `last` is set or the first one.
+    fn edge_view(&self, axis: SplitDirection, last: bool) -> String {
+        match self {
+            SplitTree::Pane(id) => id.clone(),
+            SplitTree::Split {
+                direction, second, ..
+            } if *direction == axis && last => second.edge_view(axis, last),
+            SplitTree::Split { first, .. } => first.edge_view(axis, last),
+        }
+    }
+
+    /// Search the neighbour of the pane of `view_id`.
+    ///
+    /// It returns `None` if the pane is not inside this tree and `Some(None)`
+    /// if the pane is inside but has no neighbour inside.
+    fn find_neighbour(
+        &self,
+        view_id: &str,
+        axis: SplitDirection,
+        forward: bool,
+    ) -> Option<Option<String>> {
+        match self {
+            SplitTree::Pane(id) if id == view_id => Some(None),
+            SplitTree::Pane(_) => None,
+            SplitTree::Split {
+                direction,
+                first,
+                second,
+                ..
+            } => {
+                if let Some(neighbour) = first.find_neighbour(view_id, axis, forward) {
+                    if neighbour.is_none() && *direction == axis && forward {
+                        // Enter the second pane by its side close to the
+                        // first one.
+                        return Some(Some(second.edge_view(axis, false)));
+                    }
+
+                    return Some(neighbour);
+                }
+
+                if let Some(neighbour) = second.find_neighbour(view_id, axis, forward) {
+                    if neighbour.is_none() && *direction == axis && !forward {
+                        return Some(Some(first.edge_view(axis, true)));
+                    }
+
+                    return Some(neighbour);
+                }
+
+                None
+            }
+        }
+    }
+
+    /// It returns `None` if the pane of `view_id` is not inside this tree and
+    /// if the resize has been done otherwise.
+    fn resize_pane(&mut self, view_id: &str, axis: SplitDirection, delta: i16) -> Option<bool> {
+        match self {
+            SplitTree::Pane(id) if id == view_id => Some(false),
+            SplitTree::Pane(_) => None,
+            SplitTree::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => {
+                let (done, in_first) = match first.resize_pane(view_id, axis, delta) {
+                    Some(done) => (done, true),
+                    None => (second.resize_pane(view_id, axis, delta)?, false),
+                };
+
+                if done || *direction != axis {
+                    return Some(done);
+                }
+
+                let delta = if in_first { delta } else { -delta };
+                *ratio = (i16::from(*ratio) + delta).max(MIN_RATIO).min(MAX_RATIO) as u8;
+
+                Some(true)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build the layout:
+    ///
+    /// +---+---+
+    /// |   | b |
+    /// | a +---+
+    /// |   | c |
+    /// +---+---+
+    fn three_panes() -> SplitTree {
+        let mut tree = SplitTree::new("c");
+        assert!(tree.split("c", "a", SplitDirection::Vertical));
+        assert!(tree.split("c", "b", SplitDirection::Horizontal));
+        tree
+    }
+
+    #[test]
+    fn test_split_and_close() {
+        let mut tree = three_panes();
+        assert_eq!(3, tree.pane_count());
+        assert!(tree.contains("b"));
+
+        assert_eq!(Some(String::from("c")), tree.close("b"));
+        assert_eq!(2, tree.pane_count());
+        assert!(!tree.contains("b"));
+
+        assert_eq!(Some(String::from("c")), tree.close("a"));
+        assert_eq!(SplitTree::new("c"), tree);
+
+        // The last pane can't be closed.
+        assert_eq!(None, tree.close("c"));
+    }
+
+    #[test]
+    fn test_neighbour() {
+        let tree = three_panes();
+
+        assert_eq!(
+            Some(String::from("b")),
+            tree.neighbour("a", Direction::Right)
+        );
+        assert_eq!(
+            Some(String::from("a")),
+            tree.neighbour("c", Direction::Left)
+        );
+        assert_eq!(
+            Some(String::from("c")),
+            tree.neighbour("b", Direction::Down)
+        );
+        assert_eq!(Some(String::from("b")), tree.neighbour("c", Direction::Up));
+        assert_eq!(None, tree.neighbour("a", Direction::Left));
+        assert_eq!(None, tree.neighbour("a", Direction::Up));
+        assert_eq!(None, tree.neighbour("d", Direction::Up));
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut tree = three_panes();
+
+        assert!(tree.resize("c", SplitDirection::Horizontal, 20));
+        assert!(tree.resize("c", SplitDirection::Vertical, 100));
+        assert!(!tree.resize("a", SplitDirection::Horizontal, 20));
+
+        match tree {
+            SplitTree::Split { ratio, second, .. } => {
+                assert_eq!(MIN_RATIO as u8, ratio);
+                match *second {
+                    SplitTree::Split { ratio, .. } => assert_eq!(30, ratio),
+                    _ => panic!("the second pane should be split"),
+                }
+            }
+            _ => panic!("the tree should be split"),
+        }
+    }
+}
@@ -0,0 +1,12 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+/// The state shared between the `InputController` and the `EventController`
+/// threads.
+#[derive(Debug, Default)]
+pub struct State {
+    /// The views with some changes not written into their file yet.
+    pub unsaved_views: HashSet<String>,
+}
+
+pub type SharedState = Arc<Mutex<State>>;